@@ -1,23 +1,179 @@
 // ASCII Mandelbrot in Rust
 // Single-file, no deps.
 // Usage: rustc mandelbrot.rs && ./mandelbrot w=120 h=40
-// Args: w,h,cx,cy,scale,iters
-// Designed ~100 lines for editing tasks.
+// Library: the pub items (Config, mandel_escape, shade, render, render_grid, ColorMap, ...) are the
+// embeddable core; main() and parse_args() are only the CLI wrapper. Build with
+// `rustc --crate-type=lib --cfg lib mandelbrot.rs`, or include the file as a module and
+// pass `--cfg lib` there too; the cfg quiets dead-code warnings for the CLI-only items.
+// Args: w,h,cx,cy,scale,xmin,xmax,ymin,ymax,iters,power,aa,color,out,mode,jx,jy,scene,preset,random,orbit,compare,preview,region,tile,palette,interior,interior_color,invert,dither,outer_bands,format,render,
+//       bailout,gamma,caption,sweep,animate,zoomrate,tocx,tocy,toscale,tojx,tojy,threads,maxtime,checkpoint,save,load,cycle,tour,
+//       --show-axes,--show-origin,--no-interior-skip,--time,--autoiters,--stats,--diff,--quiet,--status-stdout,--transpose,--legend,--ruler,--crosshair,--checkerboard,
+//       --ascii-only,--show-config,--repeat-render-count
 // Palette from light to dark.
 // Enjoy!
 //
+#![cfg_attr(lib, allow(dead_code))]
 use std::env;
-const PALETTE: &[u8] = b" .:-=+*#%@"; // 10 shades
-#[derive(Clone, Copy)]
-struct Config {
-    width: usize,
-    height: usize,
-    center_x: f64,
-    center_y: f64,
-    scale: f64,
-    iters: usize,
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Instant;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+const VERSION: &str = "0.3.0";
+const PALETTE: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@']; // 10 shades
+const GRAY_PALETTE: [char; 5] = [' ', '\u{2591}', '\u{2592}', '\u{2593}', '\u{2588}']; // block shades
+const OUTER_PALETTE: &[u8] = b" `'^\"~"; // faint glyphs splitting PALETTE's first band
+const PARITY_GLYPHS: [char; 2] = ['.', '#']; // even, odd escape counts
+const MIN_SCALE: f64 = 1e-15; // below this f64 coordinates stop resolving, then hit 0
+const PRECISION_LIMIT: f64 = 1e-15; // cell step, relative to |center|, where f64 stops resolving
+const MAX_SCALE: f64 = 8.0; // whole set fits; larger only wastes precision
+const MAX_TILE_LEVEL: u32 = 50; // tile=: 3 / 2^50 is about as fine as MIN_SCALE allows
+const MAX_AA: usize = 8; // aa=N costs N*N escapes per cell
+const MAX_POWER: u32 = 64; // power=N costs N - 1 complex multiplies per step
+const PERIOD_EPS: f64 = 1e-12; // orbit this close to the saved point counts as a cycle
+const PERIOD_EVERY: usize = 20; // steps between refreshes of the saved point
+const DIST_SPAN: f64 = 2.0; // color=distance: pixels from the boundary that get ink
+const DIST_BAILOUT: f64 = 1e6; // |z|^2 escape for the distance estimate; larger is more accurate
+const TRAP_SPAN: f64 = 0.5; // color=trap: orbit distances beyond this get the lightest glyph
+const CYCLE_FRAME: Duration = Duration::from_millis(80); // cycle=on: delay between palette steps
+const AUTOITERS_PER_DECADE: f64 = 100.0; // --autoiters: extra iterations per 10x zoom
+const MAX_AUTOITERS: usize = 20_000; // --autoiters never goes past this
+const NEWTON_ROOTS: [(f64, f64); 3] = [
+    (1.0, 0.0),
+    (-0.5, 0.866_025_403_784_438_6),
+    (-0.5, -0.866_025_403_784_438_6),
+];
+const NEWTON_EPS: f64 = 1e-12; // |z - root|^2 below this counts as converged
+const NEWTON_STEPS_PER_SHADE: usize = 6; // fractal=newton: steps per glyph within a basin's band
+const GAMMA_RANGE: (f64, f64) = (0.1, 10.0); // gamma= is clamped into this
+const STATS_NEAR_MAX: f64 = 0.9; // --stats: escapes after this fraction of iters count as "late"
+const CAPTION_SHARE: usize = 12; // caption= band is about 1/CAPTION_SHARE of a tall image
+const RANDOM_TRIES: usize = 64; // random=: candidate points per zoom step
+const RANDOM_ITERS: usize = 1000; // random=: budget for telling slow escapes from the interior
+const SKIPPED: usize = usize::MAX; // maxtime=: escape count of a cell that was never computed
+const SKIPPED_GLYPH: char = '?'; // ... and how it prints
+                                 // dither: 4x4 Bayer matrix; cell (x, y) rounds its palette index up past (B + 0.5) / 16
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+const RULER_COLS: usize = 16; // --ruler: columns between x labels
+const RULER_ROWS: usize = 5; // --ruler: rows between y labels
+const CHECKPOINT_ROWS: usize = 64; // checkpoint=: rows computed between saves (even: keeps --checkerboard parity)
+const CHECKPOINT_MAGIC: &[u8; 8] = b"MBCKPT01";
+const STATUS_ROWS: usize = 2; // blank line + status line under the image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Mandelbrot,
+    Julia,  // pixel is the starting z, c is fixed at (julia_x, julia_y)
+    Newton, // basins of Newton's method for z^3 - 1
 }
-impl Config {
+#[derive(Debug, Clone, PartialEq)]
+pub enum Palette {
+    Symbols,
+    Parity,                      // it % 2, exposes the striping of the escape-time field
+    Gray,                        // block shading, for displays without color
+    Custom(Vec<char>),           // palette=file:path, light to dark
+    Gradient(Vec<(u8, u8, u8)>), // palette=gradient:#rrggbb,..: truecolor blocks between the stops
+}
+impl Palette {
+    // Light-to-dark ramp; the last glyph is the interior.
+    fn glyphs(&self) -> &[char] {
+        match self {
+            Palette::Gray => &GRAY_PALETTE,
+            Palette::Custom(glyphs) => glyphs,
+            _ => &PALETTE,
+        }
+    }
+}
+pub type Rgb = [u8; 3];
+// Pluggable coloring for render_with() and compute_colors_with(): the color of escape
+// count it out of max (it >= max is the interior) at palette position smooth in [0, 1].
+pub trait ColorMap: Sync {
+    fn map(&self, it: usize, max: usize, smooth: f64) -> Rgb;
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    Linear,     // palette position proportional to the escape count
+    Histogram,  // palette position from the count's rank across the frame
+    Distance,   // exterior distance estimate: a thin outline of the boundary
+    Truecolor,  // 24-bit ANSI blocks on an HSV hue ramp
+    Trap(Trap), // closest approach of the orbit to a trap shape
+    Angle,      // argument of the orbit's final point, around the palette
+    Interior,   // interior points by |z| after max_iter; exterior as Linear
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Trap {
+    Point, // the origin
+    Cross, // the real and imaginary axes
+}
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Ascii,
+    Csv,       // raw iteration counts, one image row per line
+    Halfblock, // truecolor '\u{2580}' cells, two pixels each
+}
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub width: usize,
+    pub height: usize,
+    pub center_x: f64,
+    pub center_y: f64,
+    pub scale: f64,
+    pub bounds: Option<[f64; 4]>, // xmin, xmax, ymin, ymax; overrides center/scale
+    pub iters: usize,
+    pub autoiters: bool,           // iters grows with zoom depth
+    pub seed: Option<u64>,         // random=: the view came from this seed
+    pub sweep: Option<[usize; 3]>, // sweep=iters:start,stop,step before the final render
+    pub power: u32,                // z^power + c; 2 is the classic set
+    pub bailout: f64,              // escape radius; |z| beyond this counts as escaped
+    pub aa: usize,                 // supersample each cell on an aa x aa subgrid
+    pub interior_skip: bool,       // cardioid/bulb test and periodicity checking
+    pub out: Option<String>,       // image file instead of ASCII on stdout
+    pub caption: Option<String>,   // text along the bottom of image output
+    pub tour: Option<String>,      // script of pan/zoom/iters/render/sleep lines to replay
+    pub mode: Mode,
+    pub julia_x: f64,
+    pub julia_y: f64,
+    pub preview: usize, // render at 1/preview resolution over the same bounds
+    pub orbit: Option<(f64, f64)>, // orbit=cx,cy: plot this c's orbit instead of the set
+    pub compare: Option<String>, // compare=: arguments applied over this view for a second render
+    pub diff: bool,     // with compare=: a map of the cells whose escape counts differ
+    pub region: Option<[usize; 4]>, // x0, y0, x1, y1: only this block (x0..x1, y0..y1) of the full grid
+    pub tile: Option<(u32, u64, u64)>, // tile=z,x,y: this square of the default view, level z
+    pub checkerboard: bool,         // compute half the cells, copy the rest from a neighbour
+    pub threads: usize,             // rows are computed on this many threads
+    pub max_time: Option<Duration>, // maxtime=: rows starting later than this are skipped
+    pub checkpoint: Option<String>, // save finished rows here, and resume from them on a rerun
+    pub repeat: usize,              // >0: time this many renders instead of printing
+    pub frames: usize,              // >0: write a zoom sequence of PPM frames instead
+    pub zoom_rate: f64,             // scale multiplier between frames
+    pub zoom_to: Option<[f64; 3]>,  // animate=between: end cx, cy, scale
+    pub julia_to: Option<[f64; 2]>, // animate=julia: end jx, jy
+    pub format: Format,
+    pub palette: Palette,
+    pub interior_glyph: Option<char>, // interior=: replaces the palette's interior glyph
+    pub interior_color: Option<Rgb>, // interior_color=: the interior in truecolor, gradients and images
+    pub invert: bool,                // dark exterior, light interior
+    pub dither: bool,                // ordered dithering between neighbouring glyphs
+    pub gamma: f64, // applied to RGB output (truecolor, images); 1.0 leaves it as is
+    pub palette_offset: usize, // rotates the palette; cycle=on steps it each frame
+    pub color: ColorMode,
+    pub outer_bands: usize, // >1: split the fastest-escaping band this many ways
+    pub show_axes: bool,    // draw the real (-) and imaginary (|) axes
+    pub show_origin: bool,  // mark 0+0i with '+'
+    pub crosshair: bool,    // mark the view center with '+'
+    pub transpose: bool,    // rotate the view: the real axis runs down the rows
+    pub legend: bool,       // palette ramp line under the image
+    pub ruler: bool,        // coordinate labels above and left of the image
+    pub ascii_only: bool,   // only printable ASCII cells; other palettes and color output fall back
+    pub show_config: bool,  // print the resolved Config and exit
+    pub time: bool,         // report render wall-clock time on stderr
+    pub stats: bool,        // report escape-count statistics on stderr
+    pub cycle: bool,        // redraw with a rotating palette until Ctrl-C
+    pub quiet: bool,        // no status line
+    pub status_stdout: bool, // status as a trailing '# ' line on stdout instead of stderr
+}
+impl Default for Config {
     fn default() -> Self {
         Self {
             width: 80,
@@ -25,37 +181,1004 @@ impl Config {
             center_x: -0.5,
             center_y: 0.0,
             scale: 3.0,
+            bounds: None,
             iters: 80,
+            autoiters: false,
+            seed: None,
+            sweep: None,
+            power: 2,
+            bailout: 2.0,
+            aa: 1,
+            interior_skip: true,
+            out: None,
+            caption: None,
+            tour: None,
+            mode: Mode::Mandelbrot,
+            julia_x: -0.8,
+            julia_y: 0.156,
+            preview: 1,
+            region: None,
+            tile: None,
+            orbit: None,
+            compare: None,
+            diff: false,
+            checkerboard: false,
+            threads: 1,
+            max_time: None,
+            checkpoint: None,
+            repeat: 0,
+            frames: 0,
+            zoom_rate: 0.9,
+            zoom_to: None,
+            julia_to: None,
+            format: Format::Ascii,
+            palette: Palette::Symbols,
+            interior_glyph: None,
+            interior_color: None,
+            invert: false,
+            dither: false,
+            gamma: 1.0,
+            palette_offset: 0,
+            color: ColorMode::Linear,
+            outer_bands: 0,
+            show_axes: false,
+            show_origin: false,
+            crosshair: false,
+            transpose: false,
+            legend: false,
+            ruler: false,
+            ascii_only: false,
+            show_config: false,
+            time: false,
+            stats: false,
+            cycle: false,
+            quiet: false,
+            status_stdout: false,
+        }
+    }
+}
+impl Config {
+    // Config::builder().width(120).iters(500).build(); unset fields keep their defaults.
+    pub fn builder() -> ConfigBuilder {
+        ConfigBuilder(Self::default())
+    }
+    // Preset views; each center sits near the boundary so the frame has detail.
+    pub fn named_scene(name: &str) -> Option<Self> {
+        let (center_x, center_y, scale, iters) = match name {
+            "seahorse-valley" | "seahorse" => (-0.7453, 0.1127, 0.03, 200), // spirals between bulb and cardioid
+            "elephant-valley" | "elephant" => (0.282, 0.01, 0.03, 300), // trunks along the cardioid's cusp side
+            "triple-spiral" => (-0.0883, 0.6549, 0.06, 400), // three-armed spirals above the top bulb
+            "mini-mandelbrot" | "minibrot" => (-1.7549, 0.0, 0.08, 200), // period-3 copy on the real axis
+            _ => return None,
+        };
+        Some(Self {
+            center_x,
+            center_y,
+            scale,
+            iters,
+            ..Self::default()
+        })
+    }
+    // The grid actually sampled: preview shrinks it, region= crops it.
+    pub fn grid(&self) -> (usize, usize) {
+        match self.region {
+            Some([x0, y0, x1, y1]) => (x1 - x0, y1 - y0),
+            None => self.frame(),
+        }
+    }
+    // The whole view the coordinates are laid out over; grid() is a block of it.
+    pub fn frame(&self) -> (usize, usize) {
+        let k = self.preview.max(1);
+        (self.width.div_ceil(k), self.height.div_ceil(k))
+    }
+    // Position of grid cell (0, 0) within frame().
+    fn region_origin(&self) -> (usize, usize) {
+        self.region.map_or((0, 0), |[x0, y0, _, _]| (x0, y0))
+    }
+}
+pub struct ConfigBuilder(Config);
+impl ConfigBuilder {
+    pub fn width(mut self, width: usize) -> Self {
+        self.0.width = width;
+        self
+    }
+    pub fn height(mut self, height: usize) -> Self {
+        self.0.height = height;
+        self
+    }
+    pub fn center(mut self, x: f64, y: f64) -> Self {
+        (self.0.center_x, self.0.center_y) = (x, y);
+        self
+    }
+    pub fn scale(mut self, scale: f64) -> Self {
+        self.0.scale = scale.clamp(MIN_SCALE, MAX_SCALE);
+        self
+    }
+    // Exact rectangle; center and scale follow it as they do for xmin=.. on the command
+    // line, and the same rectangles are refused.
+    pub fn bounds(mut self, xmin: f64, xmax: f64, ymin: f64, ymax: f64) -> Result<Self, String> {
+        set_bounds(&mut self.0, [xmin, xmax, ymin, ymax])?;
+        Ok(self)
+    }
+    pub fn iters(mut self, iters: usize) -> Self {
+        self.0.iters = iters;
+        self
+    }
+    pub fn power(mut self, power: u32) -> Self {
+        self.0.power = power.clamp(2, MAX_POWER);
+        self
+    }
+    pub fn aa(mut self, aa: usize) -> Self {
+        self.0.aa = aa.clamp(1, MAX_AA);
+        self
+    }
+    pub fn julia(mut self, x: f64, y: f64) -> Self {
+        (self.0.mode, self.0.julia_x, self.0.julia_y) = (Mode::Julia, x, y);
+        self
+    }
+    pub fn palette(mut self, palette: Palette) -> Self {
+        self.0.palette = palette;
+        self
+    }
+    pub fn color(mut self, color: ColorMode) -> Self {
+        self.0.color = color;
+        self
+    }
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.0.threads = threads.max(1);
+        self
+    }
+    pub fn build(self) -> Config {
+        self.0
+    }
+}
+// Terminal size from the stdout tty, else COLUMNS/LINES. None when neither is available.
+fn terminal_size() -> Option<(usize, usize)> {
+    tty_size().or_else(|| {
+        let cols = env::var("COLUMNS").ok()?.parse().ok()?;
+        let rows = env::var("LINES").ok()?.parse().ok()?;
+        Some((cols, rows))
+    })
+}
+#[cfg(unix)]
+fn tty_size() -> Option<(usize, usize)> {
+    use std::ffi::{c_int, c_ulong};
+    #[repr(C)]
+    #[derive(Default)]
+    struct WinSize {
+        ws_row: u16,
+        ws_col: u16,
+        ws_xpixel: u16,
+        ws_ypixel: u16,
+    }
+    extern "C" {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const TIOCGWINSZ: c_ulong = 0x5413;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    const TIOCGWINSZ: c_ulong = 0x40087468; // macOS and the BSDs
+    let mut ws = WinSize::default();
+    // SAFETY: TIOCGWINSZ only writes a struct winsize through the pointer we pass.
+    let ok = unsafe { ioctl(1, TIOCGWINSZ, &mut ws as *mut WinSize) } == 0;
+    (ok && ws.ws_col > 0 && ws.ws_row > 0).then_some((ws.ws_col as usize, ws.ws_row as usize))
+}
+#[cfg(not(unix))]
+fn tty_size() -> Option<(usize, usize)> {
+    None
+}
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static TRUNCATED: AtomicBool = AtomicBool::new(false); // a render ran out of maxtime=
+                                                       // Route Ctrl-C to INTERRUPTED so a loop can stop and restore the terminal.
+#[cfg(unix)]
+fn catch_sigint() {
+    use std::ffi::c_int;
+    extern "C" {
+        fn signal(sig: c_int, handler: extern "C" fn(c_int)) -> usize;
+    }
+    extern "C" fn on_sigint(_: c_int) {
+        INTERRUPTED.store(true, Ordering::Relaxed);
+    }
+    const SIGINT: c_int = 2;
+    // SAFETY: the handler only stores to an atomic, which is async-signal-safe.
+    unsafe { signal(SIGINT, on_sigint) };
+}
+#[cfg(not(unix))]
+fn catch_sigint() {}
+// The view, plus the compare= view when there is one.
+fn parse_args() -> Result<(Config, Option<Config>), String> {
+    // cx/cy/scale stay NaN until given, so finish_config() knows which to default
+    let mut cfg = Config {
+        center_x: f64::NAN,
+        center_y: f64::NAN,
+        scale: f64::NAN,
+        ..Config::default()
+    };
+    // Fit the terminal by default; explicit w=/h= below still win.
+    if let Some((cols, rows)) = terminal_size() {
+        cfg.width = cols.max(1);
+        cfg.height = rows.saturating_sub(STATUS_ROWS).max(1);
+    }
+    let args: Vec<String> = env::args().skip(1).collect();
+    // informational flags win over everything else, wherever they appear
+    if args.iter().any(|a| a == "--version" || a == "-V") {
+        println!("mandelbrot {}", VERSION);
+        std::process::exit(0);
+    }
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        print_help();
+        std::process::exit(0);
+    }
+    // a saved view is the base that the rest of the command line overrides
+    for path in args.iter().filter_map(|a| a.strip_prefix("load=")) {
+        load_view(&mut cfg, path)?;
+    }
+    // then a preset or random view, so cx=, iters= etc. override it wherever they appear
+    let preset = |a: &&String| {
+        a.starts_with("scene=")
+            || a.starts_with("preset=")
+            || *a == "random"
+            || a.starts_with("random=")
+    };
+    for arg in args.iter().filter(preset) {
+        apply_arg(&mut cfg, arg)?;
+    }
+    let mut save = None;
+    for arg in &args {
+        if arg.starts_with("load=") || preset(&arg) {
+            continue;
+        }
+        match arg.strip_prefix("save=") {
+            Some(path) => save = Some(path),
+            None => apply_arg(&mut cfg, arg)?,
+        }
+    }
+    // compare= starts from this view as given, not from the finished one
+    let given = cfg.clone();
+    finish_config(&mut cfg)?;
+    let other = match &cfg.compare {
+        Some(args) => Some(compare_config(&given, args)?), // fails here, before any rendering
+        None => None,
+    };
+    match &other {
+        Some(other) if cfg.diff && other.grid() != cfg.grid() => {
+            return Err(
+                "--diff needs both views on the same grid; compare= changed w/h".to_string(),
+            );
+        }
+        None if cfg.diff => return Err("--diff needs compare=".to_string()),
+        _ => {}
+    }
+    if let Some(path) = save {
+        // iters= as given: --autoiters would add its extra again on load=
+        let base = Config {
+            iters: given.iters,
+            ..cfg.clone()
+        };
+        save_view(&base, path).map_err(|e| format!("saving view to '{}': {}", path, e))?;
+    }
+    Ok((cfg, other))
+}
+// Checks and settings that follow from the arguments as a whole, once all of them are
+// applied: the command line's view and a compare= view both go through here.
+fn finish_config(cfg: &mut Config) -> Result<(), String> {
+    // cx/cy/scale not given take the mode's home view; the Newton roots sit on the unit circle
+    let d = Config::default();
+    let home = match cfg.mode {
+        Mode::Newton => [0.0, 0.0, 3.0],
+        _ => [d.center_x, d.center_y, d.scale],
+    };
+    let mut view = [&mut cfg.center_x, &mut cfg.center_y, &mut cfg.scale];
+    for (v, h) in view.iter_mut().zip(home) {
+        if v.is_nan() {
+            **v = h;
+        }
+    }
+    if let Some((z, x, y)) = cfg.tile {
+        if z > MAX_TILE_LEVEL {
+            return Err(format!(
+                "tile level {} is past the precision limit ({})",
+                z, MAX_TILE_LEVEL
+            ));
+        }
+        let n = 1u64 << z;
+        if x >= n || y >= n {
+            return Err(format!(
+                "tile {},{} is outside level {} (0..{} per axis)",
+                x,
+                y,
+                z,
+                n - 1
+            ));
+        }
+        if cfg.bounds.is_some() {
+            return Err("tile= sets the bounds itself; drop xmin/xmax/ymin/ymax".to_string());
+        }
+        cfg.bounds = Some(tile_bounds(cfg, z, x, y));
+        cfg.out
+            .get_or_insert_with(|| format!("tile_{}_{}_{}.png", z, x, y));
+    }
+    if let Some([xmin, xmax, ymin, ymax]) = cfg.bounds {
+        if [xmin, xmax, ymin, ymax].iter().any(|b| b.is_nan()) {
+            return Err("xmin, xmax, ymin and ymax must be given together".to_string());
+        }
+        set_bounds(cfg, [xmin, xmax, ymin, ymax])?;
+    }
+    if let Some([_, _, x1, y1]) = cfg.region {
+        if x1 > cfg.width || y1 > cfg.height {
+            return Err(format!(
+                "region reaches {}x{}, past the {}x{} image",
+                x1, y1, cfg.width, cfg.height
+            ));
+        }
+        // these depend on cells outside the block, so a tile would not match the full render
+        if cfg.preview > 1 || cfg.checkerboard || cfg.color == ColorMode::Histogram {
+            return Err(
+                "region= cannot be combined with preview=, --checkerboard or color=histogram"
+                    .to_string(),
+            );
         }
     }
+    let many =
+        cfg.frames > 0 || cfg.repeat > 0 || cfg.tour.is_some() || cfg.cycle || cfg.sweep.is_some();
+    if cfg.checkpoint.is_some() && many {
+        let msg = "checkpoint= is for a single render, not animate=, tour=, cycle=, sweep= or --repeat-render-count";
+        return Err(msg.to_string());
+    }
+    // plain text is streamed row by row and never goes through a saved buffer
+    let buffered =
+        cfg.out.is_some() || cfg.format == Format::Csv || cfg.format == Format::Halfblock;
+    if cfg.checkpoint.is_some() && !buffered {
+        return Err("checkpoint= needs out=, format=csv or render=halfblock".to_string());
+    }
+    if cfg.ascii_only {
+        for dropped in force_ascii(cfg) {
+            eprintln!("warning: --ascii-only: ignoring {}", dropped);
+        }
+    }
+    if cfg.autoiters {
+        // iters= (or the default) is the base at scale 3
+        cfg.iters = cfg
+            .iters
+            .saturating_add(autoiters_extra(cfg.scale))
+            .min(MAX_AUTOITERS.max(cfg.iters));
+    }
+    if let Some(to) = &mut cfg.zoom_to {
+        // an unset target coordinate stays where the start view is
+        let from = [cfg.center_x, cfg.center_y, cfg.scale];
+        for (t, f) in to.iter_mut().zip(from) {
+            if t.is_nan() {
+                *t = f;
+            }
+        }
+    }
+    if let Some(to) = &mut cfg.julia_to {
+        if cfg.mode != Mode::Julia {
+            return Err("animate=julia sweeps jx/jy, so it needs mode=julia".to_string());
+        }
+        if cfg.zoom_to.is_some() {
+            return Err("animate=julia keeps the camera still; drop tocx/tocy/toscale".to_string());
+        }
+        let from = [cfg.julia_x, cfg.julia_y];
+        for (t, f) in to.iter_mut().zip(from) {
+            if t.is_nan() {
+                *t = f;
+            }
+        }
+    }
+    Ok(())
+}
+// compare=: this view with the compare= arguments applied over it, then finished.
+fn compare_config(cfg: &Config, args: &str) -> Result<Config, String> {
+    let mut other = Config {
+        compare: None,
+        ..cfg.clone()
+    };
+    for arg in args.split_whitespace() {
+        apply_arg(&mut other, arg).map_err(|e| format!("compare: {}", e))?;
+    }
+    finish_config(&mut other).map_err(|e| format!("compare: {}", e))?;
+    Ok(other)
+}
+// tile=z,x,y: the default view squared up (real -2..1, imaginary -1.5..1.5) split into
+// 2^z tiles per axis, x rightward and y down the rows. Cells sample their centers rather
+// than the tile edges, so neighbouring tiles neither repeat nor skip a line of samples.
+fn tile_bounds(cfg: &Config, z: u32, x: u64, y: u64) -> [f64; 4] {
+    let base = Config::default();
+    let side = base.scale / (1u64 << z) as f64;
+    let (left, top) = (
+        base.center_x - base.scale / 2.0,
+        base.center_y - base.scale / 2.0,
+    );
+    // half a cell in from each edge; one cell on an axis samples the middle of the
+    // bounds anyway, so that axis keeps the whole tile
+    let inset = |n: usize| if n > 1 { side / 2.0 / n as f64 } else { 0.0 };
+    let (w, h) = cfg.frame();
+    let (hx, hy) = (inset(w), inset(h));
+    let (x0, y0) = (left + side * x as f64, top + side * y as f64);
+    [x0 + hx, x0 + side - hx, y0 + hy, y0 + side - hy]
+}
+// --ascii-only: reset every setting that would print something other than a printable
+// ASCII glyph, returning what was dropped.
+fn force_ascii(cfg: &mut Config) -> Vec<&'static str> {
+    let ascii = |c: &char| c.is_ascii_graphic() || *c == ' ';
+    let mut dropped = Vec::new();
+    if !cfg.palette.glyphs().iter().all(ascii) || matches!(cfg.palette, Palette::Gradient(_)) {
+        cfg.palette = Palette::Symbols;
+        dropped.push("the requested palette");
+    }
+    if cfg.interior_glyph.is_some_and(|c| !ascii(&c)) {
+        cfg.interior_glyph = None;
+        dropped.push("interior=");
+    }
+    if cfg.color == ColorMode::Truecolor {
+        cfg.color = ColorMode::Linear;
+        dropped.push("color=truecolor");
+    }
+    if cfg.format == Format::Halfblock {
+        cfg.format = Format::Ascii;
+        dropped.push("render=halfblock");
+    }
+    dropped
+}
+// Knuth's MMIX constants; small, and the same sequence on every platform.
+struct Lcg(u64);
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        self.0 >> 33
+    }
+    // uniform in [0, 1)
+    fn unit(&mut self) -> f64 {
+        self.next() as f64 / (1u64 << 31) as f64
+    }
+}
+// random=SEED: 1 to 5 steps of 10x zoom, each onto the slowest-escaping of
+// RANDOM_TRIES points in the current view. Slow escapes sit closest to the boundary,
+// so the view keeps some of it in frame; main-body points are skipped outright.
+fn random_view(cfg: &mut Config, seed: u64) {
+    let mut rng = Lcg(seed);
+    let (mut cx, mut cy, mut scale) = (-0.75, 0.0, 2.5);
+    for _ in 0..1 + rng.next() % 5 {
+        let mut best = (cx, cy, 0);
+        for _ in 0..RANDOM_TRIES {
+            let u = cx + (rng.unit() - 0.5) * scale;
+            let v = cy + (rng.unit() - 0.5) * scale;
+            if in_main_body(u, v) {
+                continue;
+            }
+            let it = mandel_escape(0.0, 0.0, u, v, RANDOM_ITERS);
+            if it < RANDOM_ITERS && it > best.2 {
+                best = (u, v, it);
+            }
+        }
+        (cx, cy, scale) = (best.0, best.1, scale / 10.0);
+    }
+    (cfg.center_x, cfg.center_y, cfg.scale, cfg.bounds) = (cx, cy, scale, None);
+    cfg.iters = 100 + autoiters_extra(scale);
+    cfg.seed = Some(seed);
+}
+// --autoiters: iterations added for the zoom depth of scale.
+fn autoiters_extra(scale: f64) -> usize {
+    (AUTOITERS_PER_DECADE * (-(scale / 3.0).log10()).max(0.0)).round() as usize
+}
+// load=path: apply a file of key=value lines, as written by save=.
+fn load_view(cfg: &mut Config, path: &str) -> Result<(), String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("view file '{}': {}", path, e))?;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if !line.contains('=') || line.starts_with("load=") || line.starts_with("save=") {
+            return Err(format!(
+                "{}:{}: expected key=value, got '{}'",
+                path,
+                n + 1,
+                line
+            ));
+        }
+        apply_arg(cfg, line).map_err(|e| format!("{}:{}: {}", path, n + 1, e))?;
+    }
+    Ok(())
+}
+// save=path: the current view as key=value lines that load= reads back.
+fn save_view(cfg: &Config, path: &str) -> io::Result<()> {
+    let mut out = io::BufWriter::new(File::create(path)?);
+    writeln!(out, "w={}\nh={}", cfg.width, cfg.height)?;
+    writeln!(
+        out,
+        "cx={}\ncy={}\nscale={}",
+        cfg.center_x, cfg.center_y, cfg.scale
+    )?;
+    writeln!(out, "iters={}", cfg.iters)?;
+    match &cfg.palette {
+        Palette::Symbols => writeln!(out, "palette=symbols")?,
+        Palette::Parity => writeln!(out, "palette=parity")?,
+        Palette::Gray => writeln!(out, "palette=gray")?,
+        Palette::Custom(_) => writeln!(out, "# palette: custom file, not saved")?,
+        Palette::Gradient(stops) => {
+            let hex: Vec<String> = stops
+                .iter()
+                .map(|(r, g, b)| format!("#{:02x}{:02x}{:02x}", r, g, b))
+                .collect();
+            writeln!(out, "palette=gradient:{}", hex.join(","))?
+        }
+    }
+    if let Some(c) = cfg.interior_glyph {
+        writeln!(out, "interior={}", c)?;
+    }
+    if let Some([r, g, b]) = cfg.interior_color {
+        writeln!(out, "interior_color=#{:02x}{:02x}{:02x}", r, g, b)?;
+    }
+    out.flush()
+}
+fn parse_value<T: std::str::FromStr>(k: &str, v: &str) -> Result<T, String> {
+    v.parse()
+        .map_err(|_| format!("invalid value '{}' for {}", v, k))
+}
+// w= and h=: an empty grid has nothing to render and trips up every writer.
+fn parse_size(k: &str, v: &str) -> Result<usize, String> {
+    match parse_value(k, v)? {
+        0 => Err(format!("{} must be at least 1", k)),
+        n => Ok(n),
+    }
+}
+// Glyphs for palette=file:path, either one per line or all on a single line.
+fn load_palette(path: &str) -> Result<Vec<char>, String> {
+    let text =
+        std::fs::read_to_string(path).map_err(|e| format!("palette file '{}': {}", path, e))?;
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let glyphs: Vec<char> = match lines[..] {
+        [] => return Err(format!("palette file '{}' is empty", path)),
+        [line] => line.chars().collect(),
+        _ => lines.iter().flat_map(|l| l.trim().chars()).collect(),
+    };
+    if glyphs.len() < 2 {
+        return Err(format!("palette file '{}' needs at least two glyphs", path));
+    }
+    Ok(glyphs)
+}
+// Stops for palette=gradient:#000080,#ffffff,... in order from escaped-fast to interior.
+fn parse_gradient(list: &str) -> Result<Vec<(u8, u8, u8)>, String> {
+    let stops = list
+        .split(',')
+        .map(|stop| {
+            let rgb = parse_hex_color(stop)
+                .map_err(|_| format!("invalid color stop '{}' (expected #rrggbb)", stop))?;
+            Ok((rgb[0], rgb[1], rgb[2]))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if stops.len() < 2 {
+        return Err("palette=gradient needs at least two color stops".to_string());
+    }
+    Ok(stops)
+}
+// "#rrggbb" as RGB; Err(()) leaves the message to the caller.
+fn parse_hex_color(s: &str) -> Result<Rgb, ()> {
+    let hex = s
+        .trim()
+        .strip_prefix('#')
+        .filter(|h| h.len() == 6 && h.chars().all(|c| c.is_ascii_hexdigit()));
+    let hex = hex.ok_or(())?;
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+    Ok([channel(0), channel(2), channel(4)])
 }
-fn parse_args() -> Config {
-    let mut cfg = Config::default();
-    for arg in env::args().skip(1) {
-        if arg == "--help" || arg == "-h" {
-            print_help();
-            std::process::exit(0);
+// Apply one `key=value` (or `--flag`) argument to cfg.
+fn apply_arg(cfg: &mut Config, arg: &str) -> Result<(), String> {
+    let mut parts = arg.splitn(2, '=');
+    let k = parts.next().unwrap_or("");
+    let v = parts.next().unwrap_or("");
+    let bad = || format!("invalid value '{}' for {}", v, k);
+    match k {
+        "w" | "width" => cfg.width = parse_size(k, v)?,
+        "h" | "height" => cfg.height = parse_size(k, v)?,
+        "cx" => cfg.center_x = parse_value(k, v)?,
+        "cy" => cfg.center_y = parse_value(k, v)?,
+        "scale" | "s" => cfg.scale = parse_value::<f64>(k, v)?.clamp(MIN_SCALE, MAX_SCALE),
+        "xmin" | "xmax" | "ymin" | "ymax" => {
+            let i = ["xmin", "xmax", "ymin", "ymax"]
+                .iter()
+                .position(|b| *b == k)
+                .unwrap();
+            cfg.bounds.get_or_insert([f64::NAN; 4])[i] = parse_value(k, v)?;
+        }
+        "iters" | "i" => cfg.iters = parse_value(k, v)?,
+        "power" => {
+            let n = parse_value::<u32>(k, v)?;
+            if n > MAX_POWER {
+                eprintln!("warning: power={} capped at {}", n, MAX_POWER);
+            }
+            cfg.power = n.clamp(2, MAX_POWER);
+        }
+        "bailout" => {
+            cfg.bailout = parse_value(k, v)?;
+            if !(2.0..=f64::MAX).contains(&cfg.bailout) {
+                return Err(format!("bailout must be at least 2, got '{}'", v));
+            }
+        }
+        "aa" => {
+            let n: usize = parse_value::<usize>(k, v)?.max(1);
+            if n > MAX_AA {
+                eprintln!("warning: aa={} capped at {}", n, MAX_AA);
+            }
+            cfg.aa = n.min(MAX_AA);
+        }
+        "out" | "o" => cfg.out = Some(v.to_string()),
+        "caption" => cfg.caption = Some(v.to_string()).filter(|c| !c.is_empty()),
+        "tour" => cfg.tour = Some(v.to_string()),
+        "mode" | "m" | "fractal" => {
+            cfg.mode = match v {
+                "mandelbrot" => Mode::Mandelbrot,
+                "julia" => Mode::Julia,
+                "newton" => Mode::Newton,
+                _ => return Err(bad()),
+            };
+        }
+        "jx" => cfg.julia_x = parse_value(k, v)?,
+        "jy" => cfg.julia_y = parse_value(k, v)?,
+        "scene" | "preset" => {
+            let names = "seahorse, elephant, triple-spiral, minibrot";
+            let s = Config::named_scene(v)
+                .ok_or_else(|| format!("unknown {} '{}' (one of {})", k, v, names))?;
+            cfg.center_x = s.center_x;
+            cfg.center_y = s.center_y;
+            cfg.scale = s.scale;
+            cfg.iters = s.iters;
+        }
+        "sweep" => {
+            let list = v.strip_prefix("iters:").ok_or_else(bad)?;
+            let n: Vec<usize> = list
+                .split(',')
+                .map(|n| parse_value(k, n.trim()))
+                .collect::<Result<_, _>>()?;
+            match n[..] {
+                [start, stop, step] if 0 < start && start <= stop && step > 0 => {
+                    cfg.sweep = Some([start, stop, step])
+                }
+                [_, _, _] => {
+                    return Err(format!(
+                        "sweep needs 0 < start <= stop and step > 0, got '{}'",
+                        v
+                    ))
+                }
+                _ => return Err(bad()),
+            }
+        }
+        "preview" => cfg.preview = parse_value::<usize>(k, v)?.max(1),
+        "orbit" => match v.split_once(',') {
+            Some((x, y)) => {
+                cfg.orbit = Some((parse_value(k, x.trim())?, parse_value(k, y.trim())?))
+            }
+            None => return Err(bad()),
+        },
+        "compare" => cfg.compare = Some(v.to_string()),
+        "tile" => match v.split(',').map(str::trim).collect::<Vec<_>>()[..] {
+            [z, x, y] => {
+                cfg.tile = Some((parse_value(k, z)?, parse_value(k, x)?, parse_value(k, y)?))
+            }
+            _ => return Err(bad()),
+        },
+        "region" => {
+            let n: Vec<usize> = v
+                .split(',')
+                .map(|n| parse_value(k, n.trim()))
+                .collect::<Result<_, _>>()?;
+            match n[..] {
+                [x0, y0, x1, y1] if x0 < x1 && y0 < y1 => cfg.region = Some([x0, y0, x1, y1]),
+                [_, _, _, _] => {
+                    return Err(format!("region needs x0 < x1 and y0 < y1, got '{}'", v))
+                }
+                _ => return Err(bad()),
+            }
+        }
+        "maxtime" => {
+            cfg.max_time = Some(parse_value(k, v)?)
+                .filter(|&ms| ms > 0)
+                .map(Duration::from_millis)
+        }
+        "checkpoint" => cfg.checkpoint = Some(v.to_string()),
+        "threads" => {
+            cfg.threads = match parse_value(k, v)? {
+                0 => thread::available_parallelism().map_or(1, |n| n.get()),
+                n => n,
+            }
+        }
+        "palette" | "p" => {
+            cfg.palette = match v {
+                "symbols" => Palette::Symbols,
+                "parity" => Palette::Parity,
+                "gray" => Palette::Gray,
+                _ if v.starts_with("file:") => Palette::Custom(load_palette(&v[5..])?),
+                _ if v.starts_with("gradient:") => Palette::Gradient(parse_gradient(&v[9..])?),
+                _ => return Err(bad()),
+            }
+        }
+        "animate" => {
+            let n = if let Some(n) = v.strip_prefix("between:") {
+                cfg.zoom_to.get_or_insert([f64::NAN; 3]);
+                n
+            } else if let Some(n) = v.strip_prefix("julia:") {
+                cfg.mode = Mode::Julia;
+                cfg.julia_to.get_or_insert([f64::NAN; 2]);
+                n
+            } else {
+                v.strip_prefix("frames:").ok_or_else(bad)?
+            };
+            cfg.frames = parse_value(k, n)?;
+        }
+        "tocx" | "tocy" | "toscale" => {
+            let i = ["tocx", "tocy", "toscale"]
+                .iter()
+                .position(|t| *t == k)
+                .unwrap();
+            let x: f64 = parse_value(k, v)?;
+            cfg.zoom_to.get_or_insert([f64::NAN; 3])[i] = if i == 2 {
+                x.clamp(MIN_SCALE, MAX_SCALE)
+            } else {
+                x
+            };
+        }
+        "tojx" | "tojy" => {
+            let i = (k == "tojy") as usize;
+            cfg.julia_to.get_or_insert([f64::NAN; 2])[i] = parse_value(k, v)?;
+        }
+        "zoomrate" => {
+            cfg.zoom_rate = parse_value(k, v)?;
+            if cfg.zoom_rate <= 0.0 || cfg.zoom_rate.is_nan() {
+                return Err(bad());
+            }
+        }
+        "cycle" => {
+            cfg.cycle = match v {
+                "on" => true,
+                "off" => false,
+                _ => return Err(bad()),
+            }
+        }
+        "gamma" => {
+            let g: f64 = parse_value(k, v)?;
+            if g.is_nan() {
+                return Err(bad());
+            }
+            cfg.gamma = g.clamp(GAMMA_RANGE.0, GAMMA_RANGE.1);
+        }
+        "random" => {
+            // no seed: the day number, so everyone gets the same view of the day
+            let today = || {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs() / 86_400)
+            };
+            let seed = if v.is_empty() {
+                today()
+            } else {
+                parse_value(k, v)?
+            };
+            random_view(cfg, seed);
+        }
+        "interior" => {
+            let mut chars = v.chars();
+            match (chars.next(), chars.next()) {
+                // whitespace would not survive save= and load=, which trim each line
+                (Some(c), None) if !c.is_control() && !c.is_whitespace() => {
+                    cfg.interior_glyph = Some(c)
+                }
+                _ => {
+                    return Err(format!(
+                        "interior needs a single visible character, got '{}'",
+                        v
+                    ))
+                }
+            }
+        }
+        "interior_color" | "interior-color" => {
+            let rgb = parse_hex_color(v)
+                .map_err(|_| format!("invalid value '{}' for {} (expected #rrggbb)", v, k))?;
+            cfg.interior_color = Some(rgb)
+        }
+        "invert" => cfg.invert = v.is_empty() || parse_value(k, v)?,
+        "dither" => cfg.dither = v.is_empty() || parse_value(k, v)?,
+        "color" | "c" => {
+            cfg.color = match v {
+                "linear" => ColorMode::Linear,
+                "histogram" => ColorMode::Histogram,
+                "distance" => ColorMode::Distance,
+                "truecolor" => ColorMode::Truecolor,
+                "angle" => ColorMode::Angle,
+                "interior" => ColorMode::Interior,
+                "trap:point" => ColorMode::Trap(Trap::Point),
+                "trap:cross" => ColorMode::Trap(Trap::Cross),
+                _ => return Err(bad()),
+            }
         }
-        let mut parts = arg.splitn(2, '=');
-        let k = parts.next().unwrap_or("");
-        let v = parts.next().unwrap_or("");
-        match k {
-            "w" | "width" => cfg.width = v.parse().unwrap_or(cfg.width),
-            "h" | "height" => cfg.height = v.parse().unwrap_or(cfg.height),
-            "cx" => cfg.center_x = v.parse().unwrap_or(cfg.center_x),
-            "cy" => cfg.center_y = v.parse().unwrap_or(cfg.center_y),
-            "scale" | "s" => cfg.scale = v.parse().unwrap_or(cfg.scale),
-            "iters" | "i" => cfg.iters = v.parse().unwrap_or(cfg.iters),
-            _ => {}
+        "outer_bands" => cfg.outer_bands = parse_value::<usize>(k, v)?.min(OUTER_PALETTE.len()),
+        "format" | "f" => {
+            cfg.format = match v {
+                "csv" => Format::Csv,
+                "ascii" => Format::Ascii,
+                "halfblock" => Format::Halfblock,
+                _ => return Err(bad()),
+            }
         }
+        "render" => {
+            cfg.format = match v {
+                "text" | "ascii" => Format::Ascii,
+                "halfblock" => Format::Halfblock,
+                _ => return Err(bad()),
+            }
+        }
+        "--show-axes" => cfg.show_axes = true,
+        "--show-origin" => cfg.show_origin = true,
+        "--crosshair" => cfg.crosshair = true,
+        "--no-interior-skip" => cfg.interior_skip = false,
+        "--time" => cfg.time = true,
+        "--stats" => cfg.stats = true,
+        "--diff" => cfg.diff = true,
+        "--transpose" => cfg.transpose = true,
+        "--legend" => cfg.legend = true,
+        "--ruler" => cfg.ruler = true,
+        "--ascii-only" => cfg.ascii_only = true,
+        "--show-config" => cfg.show_config = true,
+        "--checkerboard" => cfg.checkerboard = true,
+        "--autoiters" => cfg.autoiters = true,
+        "--quiet" | "-q" => cfg.quiet = true,
+        "--status-stdout" => cfg.status_stdout = true,
+        "--repeat-render-count" => cfg.repeat = parse_value(k, v)?,
+        _ => return Err(format!("unknown argument '{}' (see --help)", arg)),
     }
-    cfg
+    Ok(())
 }
 fn print_help() {
     eprintln!("ASCII Mandelbrot (single file)");
-    eprintln!("Usage: mandelbrot [w=80] [h=30] [cx=-0.5] [cy=0.0] [scale=3.0] [iters=80]");
+    eprintln!(
+        "Usage: mandelbrot [w=auto] [h=auto] [cx=-0.5] [cy=0.0] [scale=3.0] [iters=80] [preview=1]"
+    );
+    eprintln!("       [xmin= xmax= ymin= ymax=]  exact rectangle; replaces cx/cy/scale and aspect");
+    eprintln!("       w/h default to the terminal size (less 2 status rows), else 80x30");
+    eprintln!("       scale is the view width; the height is scale * h / w, so every cell covers a square");
+    eprintln!("           of the plane and image output is undistorted (text looks taller: cells are ~2:1)");
+    eprintln!(
+        "       [region=x0,y0,x1,y1]  only columns x0..x1-1 and rows y0..y1-1 of the w x h view,"
+    );
+    eprintln!("           for rendering tiles separately and pasting them together");
+    eprintln!(
+        "       [tile=z,x,y]  map tile x,y of level z: the default view as a 3x3 square split"
+    );
+    eprintln!(
+        "           2^z ways per axis, y counting down the rows; out= defaults to tile_z_x_y.png"
+    );
+    eprintln!(
+        "       [--autoiters]  add {} iters per 10x zoom past scale 3 (max {})",
+        AUTOITERS_PER_DECADE, MAX_AUTOITERS
+    );
+    eprintln!(
+        "       [power=2]  Multibrot exponent, z^power + c (2 to {})",
+        MAX_POWER
+    );
+    eprintln!("       [bailout=2.0]  escape radius (>= 2); larger radii smooth gradients but");
+    eprintln!("           shift escape counts near the boundary and skip cycle detection");
+    eprintln!("       [threads=1]  compute rows in parallel (0 = one per CPU)");
+    eprintln!(
+        "       [checkpoint=state.bin]  for long image/csv renders: save the finished rows every"
+    );
+    eprintln!(
+        "           {} rows, and on a rerun with the same arguments pick up where it stopped;",
+        CHECKPOINT_ROWS
+    );
+    eprintln!("           the file is removed once the render is complete");
+    eprintln!("       [maxtime=MS]  stop starting rows after MS ms; the rest print as '{}' (0 = no limit)", SKIPPED_GLYPH);
+    eprintln!("       [aa=1]  anti-alias with an NxN subgrid per cell (max 8)");
+    eprintln!("       [out=file.ppm|png|html|svg|csv]  write an image, one pixel per cell, or");
+    eprintln!("           for .csv the raw escape counts (height lines of width values)");
+    eprintln!("       [caption=\"Seahorse Valley\"]  ASCII text in a small font along the bottom of the image");
+    eprintln!("       [mode=mandelbrot|julia|newton] [jx=-0.8] [jy=0.156]  jx/jy: Julia constant");
+    eprintln!("           newton (also fractal=newton): basins of z^3 - 1, centered on 0");
+    eprintln!("       [preset=seahorse|elephant|triple-spiral|minibrot]  famous views (cx/cy/scale/iters);");
+    eprintln!("           scene= is the same, also taking seahorse-valley, elephant-valley, mini-mandelbrot");
+    eprintln!("       [orbit=cx,cy]  instead of the set, plot z0 = 0, z1, ... for this c, each as");
+    eprintln!("           its step number mod 10 (up to iters steps, or until it escapes)");
+    eprintln!("       [compare='iters=200 bailout=4']  render the view again with these arguments");
+    eprintln!("           applied on top and print the two side by side; with --diff, a map with");
+    eprintln!("           '#' where the escape counts differ instead (w/h must stay the same)");
+    eprintln!(
+        "       [random[=SEED]]  a view near the boundary picked from SEED (default: today's"
+    );
+    eprintln!("           date); the same seed always gives the same view");
+    eprintln!(
+        "       [palette=symbols|parity|gray|file:path]  parity: '.' even, '#' odd, '@' interior"
+    );
+    eprintln!(
+        "           file: glyphs light to dark, one per line or on one line; last is the interior"
+    );
+    eprintln!(
+        "           gradient:#000080,#ffffff,#ff8000  truecolor blocks blended between the stops"
+    );
+    eprintln!(
+        "       [gamma=1.0]  RGB gamma for truecolor and image output (0.1..10); 2.2 is richer"
+    );
+    eprintln!(
+        "       [interior=@]  glyph for points inside the set (default: the palette's densest)"
+    );
+    eprintln!("       [interior_color=#rrggbb]  color of the set in truecolor, gradient and image");
+    eprintln!("           output, which otherwise leave it blank or black");
+    eprintln!("       [invert=false]  flip the palette: light interior, dark exterior");
+    eprintln!(
+        "       [dither=false]  ordered dithering: flat bands break up into a finer mix of glyphs"
+    );
+    eprintln!("       [color=linear|histogram|distance|truecolor]  histogram spreads the palette");
+    eprintln!("           by count rank, distance outlines the set (Mandelbrot, power 2),");
+    eprintln!("           truecolor draws 24-bit ANSI colored blocks");
+    eprintln!("       [color=angle]  shade escaped points by the angle of the orbit's last point");
+    eprintln!("       [color=interior]  shade points inside the set by |z| after iters steps,");
+    eprintln!("           showing banding the flat interior hides; escaped points as linear");
+    eprintln!("       [color=trap:point|trap:cross]  shade by the orbit's closest approach to");
+    eprintln!("           the origin / the axes");
+    eprintln!("       [outer_bands=N]  reveal structure far from the set (N up to 6)");
+    eprintln!("       [format=ascii|csv]  csv writes raw iteration counts to stdout");
+    eprintln!(
+        "       [render=text|halfblock]  halfblock: truecolor upper-half blocks, two square pixels"
+    );
+    eprintln!("           per cell, so the same w/h shows twice the height at double resolution");
+    eprintln!("       [tour=script.txt]  replay lines of: pan dx dy (view widths), zoom factor,");
+    eprintln!("           iters N, render, sleep ms");
+    eprintln!("       [cycle=on]  redraw the view with a rotating palette until Ctrl-C");
+    eprintln!("       [save=view.txt] [load=view.txt]  store w,h,cx,cy,scale,iters,palette;");
+    eprintln!("           load is applied first, so later arguments override it");
+    eprintln!("       [--show-axes] [--show-origin]  overlay the axes / mark 0+0i when in view");
+    eprintln!("       [--crosshair]  mark the view center (cx, cy) with '+'");
+    eprintln!(
+        "       [--no-interior-skip]  no cardioid/bulb or cycle shortcuts (for benchmarking)"
+    );
+    eprintln!(
+        "       [--quiet] [--status-stdout]  drop the status line / append it to stdout as '# ...'"
+    );
+    eprintln!(
+        "       [--checkerboard]  rough ASCII preview: compute every other cell, fill the rest"
+    );
+    eprintln!("       [--legend]  add a line showing the palette from interior to exterior");
+    eprintln!(
+        "       [--ruler]  label the column coordinate every {} cells above the image and",
+        RULER_COLS
+    );
+    eprintln!(
+        "           the row coordinate every {} rows in a left margin",
+        RULER_ROWS
+    );
+    eprintln!("       [--ascii-only]  single-byte cells for pipes and plain terminals: non-ASCII");
+    eprintln!(
+        "           palettes, interior= glyphs and color output fall back to the standard palette"
+    );
+    eprintln!("       [--transpose]  real axis down the rows, for tall narrow terminals");
+    eprintln!("       [--show-config]  print every resolved setting (after load=, defaults, the");
+    eprintln!("           terminal size and overrides) and exit without rendering");
+    eprintln!("       [--version]  print the version and exit");
+    eprintln!("       [--time]  print the render time and Mpixel/s after the image");
+    eprintln!(
+        "       [sweep=iters:start,stop,step]  print the interior share at each iters on stderr,"
+    );
+    eprintln!("           then render at stop");
+    eprintln!("       [--stats]  print min/max/mean escape count and the interior share, with");
+    eprintln!("           a hint when iters looks too high or too low for the view");
+    eprintln!(
+        "       [animate=frames:N] [zoomrate=0.9]  write frame_0000.ppm.. zooming on the center"
+    );
+    eprintln!(
+        "       [animate=between:N tocx= tocy= toscale=]  N frames from this view to the target;"
+    );
+    eprintln!(
+        "       [animate=julia:N tojx= tojy=]  N frames of the Julia set as c moves from jx,jy"
+    );
+    eprintln!("           to the target in a straight line; c just outside the main cardioid");
+    eprintln!("           morphs best, e.g. jx=-0.8 jy=0.156 tojx=-0.7 tojy=0.27 or along the");
+    eprintln!("           top edge from jx=0.285 jy=0.01 to tojx=-0.12 tojy=0.75");
+    eprintln!("           out=*.png writes PNG frames instead");
+    eprintln!("       [--repeat-render-count=N]  time N renders of this view, print avg/min/max");
 }
-fn mandel_escape(mut zx: f64, mut zy: f64, cx: f64, cy: f64, max_iter: usize) -> usize {
+pub fn mandel_escape(mut zx: f64, mut zy: f64, cx: f64, cy: f64, max_iter: usize) -> usize {
     let mut i = 0;
     while zx * zx + zy * zy <= 4.0 && i < max_iter {
         let x2 = zx * zx - zy * zy + cx;
@@ -66,35 +1189,2566 @@ fn mandel_escape(mut zx: f64, mut zy: f64, cx: f64, cy: f64, max_iter: usize) ->
     }
     i
 }
-fn shade(it: usize, max_iter: usize) -> char {
-    if it >= max_iter {
-        return '@';
+// Same loop for z^power + c, with the power taken by repeated complex multiplication.
+pub fn multibrot_escape(zx: f64, zy: f64, cx: f64, cy: f64, power: u32, max_iter: usize) -> usize {
+    escape_within(zx, zy, cx, cy, power, max_iter, 4.0)
+}
+// multibrot_escape with the escape test |z|^2 <= bailout2 in place of radius 2.
+pub fn escape_within(
+    mut zx: f64,
+    mut zy: f64,
+    cx: f64,
+    cy: f64,
+    power: u32,
+    max_iter: usize,
+    bailout2: f64,
+) -> usize {
+    let mut i = 0;
+    while zx * zx + zy * zy <= bailout2 && i < max_iter {
+        let (mut px, mut py) = (zx, zy);
+        for _ in 1..power {
+            (px, py) = (px * zx - py * zy, px * zy + py * zx);
+        }
+        zx = px + cx;
+        zy = py + cy;
+        i += 1;
     }
-    let t = it as f64 / max_iter as f64;
-    let idx = (t * (PALETTE.len() as f64 - 1.0)).round() as usize;
-    PALETTE[idx] as char
-}
-fn render(cfg: Config) -> String {
-    let mut out = String::with_capacity((cfg.width + 1) * cfg.height);
-    let (w, h) = (cfg.width as f64, cfg.height as f64);
-    let aspect = w / h; // adjust vertical scale for terminal cells
-    for y in 0..cfg.height {
-        let v = (y as f64 / (h - 1.0) - 0.5) * cfg.scale / aspect + cfg.center_y;
-        for x in 0..cfg.width {
-            let u = (x as f64 / (w - 1.0) - 0.5) * cfg.scale + cfg.center_x;
-            let it = mandel_escape(0.0, 0.0, u, v, cfg.iters);
-            out.push(shade(it, cfg.iters));
+    i
+}
+// mandel_escape from z = 0 for four points at once. The lanes are plain arrays so
+// the loop autovectorizes; live masks out lanes that have escaped, and the loop
+// ends when no lane is live. Counts match mandel_escape lane for lane.
+pub fn mandel_escape_x4(cx: [f64; 4], cy: [f64; 4], max_iter: usize) -> [usize; 4] {
+    let (mut zx, mut zy) = ([0.0f64; 4], [0.0f64; 4]);
+    let mut its = [0usize; 4];
+    for _ in 0..max_iter {
+        let live: [bool; 4] = std::array::from_fn(|l| zx[l] * zx[l] + zy[l] * zy[l] <= 4.0);
+        if live == [false; 4] {
+            break;
+        }
+        for l in 0..4 {
+            let x2 = zx[l] * zx[l] - zy[l] * zy[l] + cx[l];
+            let y2 = 2.0 * zx[l] * zy[l] + cy[l];
+            // escaped lanes keep their last z so it cannot overflow to NaN
+            zx[l] = if live[l] { x2 } else { zx[l] };
+            zy[l] = if live[l] { y2 } else { zy[l] };
+            its[l] += live[l] as usize;
         }
-        out.push('\n');
     }
-    out
+    its
 }
-fn main() {
-    let cfg = parse_args();
-    let img = render(cfg);
-    println!("{}", img);
-    eprintln!(
-        "w={} h={} cx={:.5} cy={:.5} scale={} iters={}",
-        cfg.width, cfg.height, cfg.center_x, cfg.center_y, cfg.scale, cfg.iters
-    );
+// mandel_escape with periodicity checking: an orbit that returns to a saved point is
+// cycling and will never escape, so it reports max_iter early.
+pub fn mandel_escape_periodic(
+    mut zx: f64,
+    mut zy: f64,
+    cx: f64,
+    cy: f64,
+    max_iter: usize,
+) -> usize {
+    let (mut ox, mut oy) = (zx, zy);
+    let mut i = 0;
+    while zx * zx + zy * zy <= 4.0 && i < max_iter {
+        let x2 = zx * zx - zy * zy + cx;
+        let y2 = 2.0 * zx * zy + cy;
+        zx = x2;
+        zy = y2;
+        i += 1;
+        if (zx - ox).abs() < PERIOD_EPS && (zy - oy).abs() < PERIOD_EPS {
+            return max_iter;
+        }
+        if i % PERIOD_EVERY == 0 {
+            (ox, oy) = (zx, zy);
+        }
+    }
+    i
+}
+// Exterior distance estimate |z| ln|z| / |dz| for c, iterating dz = 2 z dz + 1 beside z.
+// None when c does not escape within max_iter.
+pub fn mandel_distance(cx: f64, cy: f64, max_iter: usize) -> Option<f64> {
+    let (mut zx, mut zy, mut dx, mut dy) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+    for _ in 0..max_iter {
+        (dx, dy) = (2.0 * (zx * dx - zy * dy) + 1.0, 2.0 * (zx * dy + zy * dx));
+        (zx, zy) = (zx * zx - zy * zy + cx, 2.0 * zx * zy + cy);
+        let r2 = zx * zx + zy * zy;
+        if r2 > DIST_BAILOUT {
+            let r = r2.sqrt();
+            return Some(r * r.ln() / (dx * dx + dy * dy).sqrt());
+        }
+    }
+    None
+}
+// escape_within, together with the orbit's final z.
+pub fn escape_with_final(
+    mut zx: f64,
+    mut zy: f64,
+    cx: f64,
+    cy: f64,
+    power: u32,
+    max_iter: usize,
+    bailout2: f64,
+) -> (usize, f64, f64) {
+    let mut i = 0;
+    while zx * zx + zy * zy <= bailout2 && i < max_iter {
+        let (mut px, mut py) = (zx, zy);
+        for _ in 1..power {
+            (px, py) = (px * zx - py * zy, px * zy + py * zx);
+        }
+        zx = px + cx;
+        zy = py + cy;
+        i += 1;
+    }
+    (i, zx, zy)
+}
+// escape_with_final() for cell (x, y) of the view, at its bailout= radius.
+fn final_z(cfg: &Config, x: usize, y: usize) -> (usize, f64, f64) {
+    let (u, v) = cell_to_complex(cfg, x, y);
+    let (zx, zy, cx, cy) = match cfg.mode {
+        Mode::Julia => (u, v, cfg.julia_x, cfg.julia_y),
+        _ => (0.0, 0.0, u, v),
+    };
+    escape_with_final(
+        zx,
+        zy,
+        cx,
+        cy,
+        cfg.power,
+        cfg.iters,
+        cfg.bailout * cfg.bailout,
+    )
+}
+// Palette position for color=angle: arg(z) of the escaped point, once around in [0, 1).
+fn angle_position(cfg: &Config, x: usize, y: usize) -> f64 {
+    let (_, zx, zy) = final_z(cfg, x, y);
+    (zy.atan2(zx) / std::f64::consts::TAU).rem_euclid(1.0)
+}
+// Palette position for color=interior: |z| after max_iter steps, which stays within the
+// escape radius 2 for points that never escaped.
+fn interior_position(cfg: &Config, x: usize, y: usize) -> f64 {
+    let (_, zx, zy) = final_z(cfg, x, y);
+    (zx.hypot(zy) / 2.0).min(1.0)
+}
+// Smallest distance from the orbit of z under z^power + c to the trap, over the
+// iterations up to escape or max_iter.
+pub fn orbit_trap(
+    mut zx: f64,
+    mut zy: f64,
+    cx: f64,
+    cy: f64,
+    power: u32,
+    max_iter: usize,
+    trap: Trap,
+) -> f64 {
+    let mut best = f64::INFINITY;
+    for _ in 0..max_iter {
+        if zx * zx + zy * zy > 4.0 {
+            break;
+        }
+        let (mut px, mut py) = (zx, zy);
+        for _ in 1..power {
+            (px, py) = (px * zx - py * zy, px * zy + py * zx);
+        }
+        zx = px + cx;
+        zy = py + cy;
+        let d = match trap {
+            Trap::Point => (zx * zx + zy * zy).sqrt(),
+            Trap::Cross => zx.abs().min(zy.abs()),
+        };
+        best = best.min(d);
+    }
+    best
+}
+// Palette position for color=trap: 1 on the trap fading to 0 TRAP_SPAN away.
+fn trap_position(cfg: &Config, x: usize, y: usize, trap: Trap) -> f64 {
+    let (u, v) = cell_to_complex(cfg, x, y);
+    let d = match cfg.mode {
+        Mode::Julia => orbit_trap(u, v, cfg.julia_x, cfg.julia_y, cfg.power, cfg.iters, trap),
+        _ => orbit_trap(0.0, 0.0, u, v, cfg.power, cfg.iters, trap),
+    };
+    1.0 - (d / TRAP_SPAN).min(1.0)
+}
+// Newton's method for z^3 - 1 from z: which cube root of unity the iteration
+// settles on (None if it has not within max_iter) and the steps it took.
+pub fn newton_root(mut zx: f64, mut zy: f64, max_iter: usize) -> (Option<usize>, usize) {
+    for i in 0..max_iter {
+        for (k, &(rx, ry)) in NEWTON_ROOTS.iter().enumerate() {
+            if (zx - rx) * (zx - rx) + (zy - ry) * (zy - ry) < NEWTON_EPS {
+                return (Some(k), i);
+            }
+        }
+        let (sx, sy) = (zx * zx - zy * zy, 2.0 * zx * zy); // z^2
+        let (nx, ny) = (sx * zx - sy * zy - 1.0, sx * zy + sy * zx); // z^3 - 1
+        let d = 3.0 * (sx * sx + sy * sy); // (z^3 - 1) / 3z^2 = n * conj(z^2) / (3|z^2|^2)
+        if d == 0.0 {
+            return (None, i);
+        }
+        zx -= (nx * sx + ny * sy) / d;
+        zy -= (ny * sx - nx * sy) / d;
+    }
+    (None, max_iter)
+}
+// fractal=newton glyph: the palette is cut into one band per root and the steps
+// to converge pick the glyph within it. Unconverged points take the interior glyph.
+fn newton_glyph(cfg: &Config, (root, steps): (Option<usize>, usize)) -> char {
+    let glyphs = cfg.palette.glyphs();
+    let last = glyphs.len() - 1;
+    let idx = match root {
+        Some(r) => {
+            let band = (glyphs.len() / 3).max(1);
+            (r * band + (steps / NEWTON_STEPS_PER_SHADE).min(band - 1)).min(last)
+        }
+        None => last,
+    };
+    glyphs[if cfg.invert { last - idx } else { idx }]
+}
+// Image color for fractal=newton: red, green or blue by root, darker the longer it took.
+fn newton_color((root, steps): (Option<usize>, usize)) -> [u8; 3] {
+    let Some(r) = root else {
+        return [0, 0, 0];
+    };
+    let base = [
+        [230.0, 70.0, 60.0],
+        [80.0, 200.0, 90.0],
+        [70.0, 110.0, 230.0],
+    ][r];
+    let v = 0.3 + 0.7 * 0.92f64.powi(steps as i32);
+    base.map(|c: f64| (c * v).round() as u8)
+}
+// Palette position for color=distance: 1 on the boundary fading to 0 DIST_SPAN pixels out.
+fn distance_position(cfg: &Config, x: usize, y: usize) -> f64 {
+    let (u, v) = cell_to_complex(cfg, x, y);
+    let (w, h) = cfg.frame();
+    let step = cfg.scale / (if cfg.transpose { h } else { w }.max(2) - 1) as f64;
+    match mandel_distance(u, v, cfg.iters) {
+        Some(d) => 1.0 - (d / step / DIST_SPAN).min(1.0),
+        None => 1.0,
+    }
+}
+// True when c lies in the main cardioid or the period-2 bulb, which never escape.
+pub fn in_main_body(cx: f64, cy: f64) -> bool {
+    if cx * cx + cy * cy > 4.0 {
+        return false; // far enough out that q below would overflow to inf <= inf
+    }
+    let q = (cx - 0.25) * (cx - 0.25) + cy * cy;
+    q * (q + (cx - 0.25)) <= 0.25 * cy * cy || (cx + 1.0) * (cx + 1.0) + cy * cy <= 1.0 / 16.0
+}
+// Escape count for start z and constant c under the configured power.
+fn escape(cfg: &Config, zx: f64, zy: f64, cx: f64, cy: f64) -> usize {
+    let from_zero = zx == 0.0 && zy == 0.0;
+    if cfg.interior_skip && cfg.power == 2 && from_zero && in_main_body(cx, cy) {
+        return cfg.iters;
+    }
+    if cfg.bailout != 2.0 {
+        // the radius-2 loops below are the fast paths; other radii take the general one
+        escape_within(
+            zx,
+            zy,
+            cx,
+            cy,
+            cfg.power,
+            cfg.iters,
+            cfg.bailout * cfg.bailout,
+        )
+    } else if cfg.power == 2 && cfg.interior_skip {
+        mandel_escape_periodic(zx, zy, cx, cy, cfg.iters)
+    } else if cfg.power == 2 {
+        mandel_escape(zx, zy, cx, cy, cfg.iters) // exact fast path
+    } else {
+        multibrot_escape(zx, zy, cx, cy, cfg.power, cfg.iters)
+    }
+}
+pub fn shade(it: usize, cfg: &Config) -> char {
+    shade_at(it, it as f64 / cfg.iters as f64, 0.5, cfg)
+}
+// Shade with an explicit palette position t in [0, 1) for escaped points. The glyph
+// index rounds up once its fraction reaches threshold: 0.5 is plain rounding.
+fn shade_at(it: usize, t: f64, threshold: f64, cfg: &Config) -> char {
+    let glyphs = cfg.palette.glyphs();
+    let last = glyphs.len() - 1;
+    if it >= cfg.iters {
+        return interior_glyph(cfg);
+    }
+    if cfg.palette == Palette::Parity {
+        return PARITY_GLYPHS[(it + cfg.invert as usize + cfg.palette_offset) % 2];
+    }
+    let pos = t * last as f64;
+    let idx = ((pos + threshold) as usize + cfg.palette_offset) % glyphs.len();
+    if cfg.invert {
+        return glyphs[last - idx];
+    }
+    if idx == 0 && cfg.outer_bands > 1 && cfg.palette == Palette::Symbols {
+        // pos is in [0, 0.5) here; spread the sub-bands across OUTER_PALETTE
+        let n = cfg.outer_bands;
+        let k = ((pos * 2.0 * n as f64) as usize).min(n - 1);
+        return OUTER_PALETTE[k * (OUTER_PALETTE.len() - 1) / (n - 1)] as char;
+    }
+    glyphs[idx]
+}
+// The glyph for points that never escaped: interior= or the palette's end.
+fn interior_glyph(cfg: &Config) -> char {
+    let glyphs = cfg.palette.glyphs();
+    cfg.interior_glyph
+        .unwrap_or(glyphs[if cfg.invert { 0 } else { glyphs.len() - 1 }])
+}
+// A blank cell, or an interior_color= block, for the interior in color output.
+fn interior_cell(cfg: &Config) -> String {
+    cfg.interior_color
+        .map_or(" ".to_string(), |rgb| rgb_cell(rgb, cfg.gamma))
+}
+// Complex coordinate sampled by cell (x, y) of the sampled grid.
+pub fn cell_to_complex(cfg: &Config, x: usize, y: usize) -> (f64, f64) {
+    point_to_complex(cfg, x as f64, y as f64)
+}
+// As cell_to_complex, for fractional cell positions (supersampling).
+fn point_to_complex(cfg: &Config, x: f64, y: f64) -> (f64, f64) {
+    let aspect = cfg.width as f64 / cfg.height as f64; // rows span scale / aspect: square cells in the plane
+    let (w, h) = cfg.frame(); // keep the full-size aspect so a preview frames the same region
+    let (w, h) = (w as f64, h as f64);
+    let (x0, y0) = cfg.region_origin();
+    let (x, y) = (x + x0 as f64, y + y0 as f64);
+    // transposed, map as the untransposed view of the swapped grid
+    let (x, y, w, h, aspect) = if cfg.transpose {
+        (y, x, h, w, 1.0 / aspect)
+    } else {
+        (x, y, w, h, aspect)
+    };
+    // a single row/column samples the center line instead of dividing by zero
+    let tx = if w > 1.0 { x / (w - 1.0) } else { 0.5 };
+    let ty = if h > 1.0 { y / (h - 1.0) } else { 0.5 };
+    if let Some([xmin, xmax, ymin, ymax]) = cfg.bounds {
+        return (xmin + tx * (xmax - xmin), ymin + ty * (ymax - ymin));
+    }
+    let u = (tx - 0.5) * cfg.scale + cfg.center_x;
+    let v = (ty - 0.5) * cfg.scale / aspect + cfg.center_y;
+    (u, v)
+}
+// Inverse of cell_to_complex: the nearest cell, or None when the point is off-screen.
+pub fn complex_to_cell(cfg: &Config, u: f64, v: f64) -> Option<(usize, usize)> {
+    frame_cell(cfg, u, v).and_then(|c| grid_cell(cfg, c))
+}
+// complex_to_cell() in frame coordinates, before region= crops the grid.
+fn frame_cell(cfg: &Config, u: f64, v: f64) -> Option<(usize, usize)> {
+    let aspect = cfg.width as f64 / cfg.height as f64;
+    let (w, h) = cfg.frame();
+    let (w, h) = (w as f64, h as f64);
+    let (w, h, aspect) = if cfg.transpose {
+        (h, w, 1.0 / aspect)
+    } else {
+        (w, h, aspect)
+    };
+    let (tx, ty) = match cfg.bounds {
+        Some([xmin, xmax, ymin, ymax]) => ((u - xmin) / (xmax - xmin), (v - ymin) / (ymax - ymin)),
+        None => (
+            (u - cfg.center_x) / cfg.scale + 0.5,
+            (v - cfg.center_y) * aspect / cfg.scale + 0.5,
+        ),
+    };
+    let (x, y) = ((tx * (w - 1.0)).round(), (ty * (h - 1.0)).round());
+    let on_screen = (0.0..w).contains(&x) && (0.0..h).contains(&y);
+    let (x, y) = if cfg.transpose { (y, x) } else { (x, y) };
+    on_screen.then_some((x as usize, y as usize))
+}
+// Escape counts at the aa x aa subsample points of cell (x, y); one point when aa=1.
+fn cell_samples(cfg: &Config, x: usize, y: usize) -> Vec<usize> {
+    sample_points(cfg, x, y)
+        .into_iter()
+        .map(|(u, v)| match cfg.mode {
+            Mode::Mandelbrot => escape(cfg, 0.0, 0.0, u, v),
+            Mode::Julia => escape(cfg, u, v, cfg.julia_x, cfg.julia_y),
+            Mode::Newton => newton_root(u, v, cfg.iters).1,
+        })
+        .collect()
+}
+// Points sampled for cell (x, y): its center, or an aa x aa subgrid.
+fn sample_points(cfg: &Config, x: usize, y: usize) -> Vec<(f64, f64)> {
+    let n = cfg.aa.max(1);
+    let offset = |i: usize| (i as f64 + 0.5) / n as f64 - 0.5;
+    (0..n * n)
+        .map(|k| point_to_complex(cfg, x as f64 + offset(k % n), y as f64 + offset(k / n)))
+        .collect()
+}
+// Escape counts for one row of the sampled grid, averaged over any subsamples.
+pub fn compute_row(cfg: &Config, y: usize) -> Vec<usize> {
+    let w = cfg.grid().0;
+    let mut row = Vec::with_capacity(w);
+    // Brute force goes four cells per mandel_escape_x4 call. With interior_skip the
+    // scalar path wins: cardioid and cycle exits beat lanes that must run to max_iter.
+    let lanes = !cfg.interior_skip
+        && !cfg.checkerboard
+        && cfg.aa <= 1
+        && cfg.mode == Mode::Mandelbrot
+        && cfg.power == 2
+        && cfg.bailout == 2.0;
+    while lanes && row.len() + 4 <= w {
+        let x = row.len();
+        let c: [(f64, f64); 4] = std::array::from_fn(|l| cell_to_complex(cfg, x + l, y));
+        row.extend(mandel_escape_x4(c.map(|c| c.0), c.map(|c| c.1), cfg.iters));
+    }
+    for x in row.len()..w {
+        if cfg.checkerboard && x > 0 && (x + y) % 2 == 1 {
+            row.push(row[x - 1]); // the left neighbour is always a computed cell
+            continue;
+        }
+        let s = cell_samples(cfg, x, y);
+        row.push((s.iter().sum::<usize>() + s.len() / 2) / s.len());
+    }
+    row
+}
+// Escape counts for the sampled grid, row-major.
+pub fn compute_iterations(cfg: &Config) -> Vec<usize> {
+    match &cfg.checkpoint {
+        Some(path) => checkpointed(cfg, path, compute_iterations),
+        None => parallel_rows(cfg, false, |y| compute_row(cfg, y)),
+    }
+}
+// A grid cell as stored in a checkpoint= file.
+trait Record: Sized {
+    const SIZE: usize;
+    fn put(&self, out: &mut Vec<u8>);
+    fn get(bytes: &[u8]) -> Self;
+}
+impl Record for usize {
+    const SIZE: usize = 8;
+    fn put(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&(*self as u64).to_le_bytes());
+    }
+    fn get(bytes: &[u8]) -> Self {
+        let mut le = [0; 8];
+        le.copy_from_slice(bytes);
+        u64::from_le_bytes(le) as usize
+    }
+}
+impl Record for Rgb {
+    const SIZE: usize = 3;
+    fn put(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self);
+    }
+    fn get(bytes: &[u8]) -> Self {
+        [bytes[0], bytes[1], bytes[2]]
+    }
+}
+// checkpoint=: the grid CHECKPOINT_ROWS rows at a time, each band computed by rows()
+// on a region= of just those rows, and everything so far saved after each band. The
+// file starts with CHECKPOINT_MAGIC, a key for the view, the record size and the cell
+// count; a file whose header does not match is ignored and overwritten, and the file
+// is removed once the grid is done. I/O errors only cost the checkpointing, never the render.
+fn checkpointed<T: Record>(cfg: &Config, path: &str, rows: impl Fn(&Config) -> Vec<T>) -> Vec<T> {
+    let plain = Config {
+        checkpoint: None,
+        ..cfg.clone()
+    };
+    let (w, h) = cfg.grid();
+    let key = checkpoint_key(cfg);
+    let mut cells: Vec<T> = match std::fs::read(path) {
+        Ok(bytes) => match read_checkpoint(&bytes, key, w * h) {
+            Some(cells) => {
+                eprintln!(
+                    "checkpoint: resuming '{}' at row {} of {}",
+                    path,
+                    cells.len() / w.max(1),
+                    h
+                );
+                cells
+            }
+            None => {
+                eprintln!(
+                    "warning: checkpoint '{}' is for another view; starting over",
+                    path
+                );
+                Vec::new()
+            }
+        },
+        Err(_) => Vec::new(),
+    };
+    let mut done = cells.len() / w.max(1);
+    while done < h {
+        let end = (done + CHECKPOINT_ROWS).min(h);
+        let (x0, y0) = cfg.region_origin();
+        cells.extend(rows(&Config {
+            region: Some([x0, y0 + done, x0 + w, y0 + end]),
+            ..plain.clone()
+        }));
+        done = end;
+        if let Err(e) = write_checkpoint(path, key, &cells) {
+            eprintln!(
+                "warning: checkpoint '{}': {}; finishing without it",
+                path, e
+            );
+            if done < h {
+                cells.extend(rows(&Config {
+                    region: Some([x0, y0 + done, x0 + w, y0 + h]),
+                    ..plain
+                }));
+            }
+            break;
+        }
+    }
+    // finished: a later run computes afresh instead of trusting cells from an older build
+    let _ = std::fs::remove_file(path);
+    cells
+}
+// Everything but the output and bookkeeping settings, so a rerun with other out=,
+// threads= or status flags still resumes.
+fn checkpoint_key(cfg: &Config) -> u32 {
+    let view = Config {
+        out: None,
+        checkpoint: None,
+        caption: None,
+        threads: 1,
+        max_time: None,
+        time: false,
+        stats: false,
+        quiet: false,
+        status_stdout: false,
+        ..cfg.clone()
+    };
+    crc32(format!("{:?}", view).as_bytes())
+}
+// The cells of a checkpoint file, or None when it is not one for this key and grid.
+fn read_checkpoint<T: Record>(bytes: &[u8], key: u32, cells: usize) -> Option<Vec<T>> {
+    let field = |i: usize| bytes.get(8 + 8 * i..16 + 8 * i).map(<usize as Record>::get);
+    let (k, size, n) = (field(0)?, field(1)?, field(2)?);
+    let body = &bytes[32..];
+    let ok = bytes.starts_with(CHECKPOINT_MAGIC) && k == key as usize && size == T::SIZE;
+    (ok && n <= cells && body.len() == n * T::SIZE)
+        .then(|| body.chunks(T::SIZE).map(T::get).collect())
+}
+fn write_checkpoint<T: Record>(path: &str, key: u32, cells: &[T]) -> io::Result<()> {
+    let mut bytes = CHECKPOINT_MAGIC.to_vec();
+    for field in [key as u64, T::SIZE as u64, cells.len() as u64] {
+        bytes.extend_from_slice(&field.to_le_bytes());
+    }
+    for cell in cells {
+        cell.put(&mut bytes);
+    }
+    // write then rename, so an interrupted save leaves the last good checkpoint
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)
+}
+// compute_row(), unless the maxtime= budget counted from start is spent: then a row of
+// SKIPPED, checked once per row so the test costs nothing next to the row itself.
+fn budget_row(cfg: &Config, start: Instant, y: usize) -> Vec<usize> {
+    match cfg.max_time {
+        Some(limit) if start.elapsed() > limit => {
+            TRUNCATED.store(true, Ordering::Relaxed);
+            vec![SKIPPED; cfg.grid().0]
+        }
+        _ => compute_row(cfg, y),
+    }
+}
+// row(y) for every row of the sampled grid on cfg.threads threads, concatenated in
+// order.
+fn parallel_rows<T: Send>(
+    cfg: &Config,
+    chunked: bool,
+    row: impl Fn(usize) -> Vec<T> + Sync,
+) -> Vec<T> {
+    let mut all = Vec::new();
+    let _ = each_row(cfg, chunked, row, |r| {
+        all.extend(r);
+        Ok(())
+    });
+    all
+}
+// row(y) for every row of the sampled grid on cfg.threads threads, passed to emit in
+// order as soon as every row above it is done; rows that finish early wait in a
+// reorder buffer. Rows are handed out one at a time from a shared counter, so threads
+// that draw cheap exterior rows keep working while others are stuck in the interior;
+// chunked gives each thread one contiguous band instead (kept for --time).
+// With fewer rows than threads only h threads are started, so no chunked band is
+// empty. An error from emit stops the workers after the rows they are on.
+fn each_row<T: Send>(
+    cfg: &Config,
+    chunked: bool,
+    row: impl Fn(usize) -> Vec<T> + Sync,
+    mut emit: impl FnMut(Vec<T>) -> io::Result<()>,
+) -> io::Result<()> {
+    let h = cfg.grid().1;
+    let n = cfg.threads.clamp(1, h.max(1));
+    if n == 1 {
+        return (0..h).try_for_each(|y| emit(row(y)));
+    }
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    thread::scope(|s| {
+        for t in 0..n {
+            let (row, next, tx) = (&row, &next, tx.clone());
+            s.spawn(move || {
+                let mut band = t * h / n..(t + 1) * h / n;
+                loop {
+                    let y = if chunked {
+                        band.next()
+                    } else {
+                        Some(next.fetch_add(1, Ordering::Relaxed))
+                    };
+                    match y {
+                        // a failed send means the receiver gave up after an error
+                        Some(y) if y < h => {
+                            if tx.send((y, row(y))).is_err() {
+                                return;
+                            }
+                        }
+                        _ => return,
+                    }
+                }
+            });
+        }
+        drop(tx);
+        let mut pending: Vec<Option<Vec<T>>> = (0..h).map(|_| None).collect();
+        let mut y = 0;
+        for (done, r) in rx {
+            pending[done] = Some(r);
+            while let Some(r) = pending.get_mut(y).and_then(Option::take) {
+                emit(r)?;
+                y += 1;
+            }
+        }
+        Ok(())
+    })
+}
+// --stats: summary of an escape-count buffer, plus a hint for tuning iters when the
+// view is mostly interior or a noticeable share of escapes come in late.
+pub fn iteration_stats(its: &[usize], max_iter: usize) -> String {
+    if its.is_empty() {
+        return "stats: empty view".to_string();
+    }
+    let (min, max) = (its.iter().min().unwrap(), its.iter().max().unwrap());
+    let mean = its.iter().sum::<usize>() as f64 / its.len() as f64;
+    let interior = interior_share(its, max_iter);
+    let escaped = its.iter().filter(|&&it| it < max_iter).count();
+    let late = its
+        .iter()
+        .filter(|&&it| it < max_iter && it as f64 >= STATS_NEAR_MAX * max_iter as f64)
+        .count();
+    let share = |n: usize, of: usize| 100.0 * n as f64 / of.max(1) as f64;
+    let mut out = format!(
+        "stats: escape count min {} max {} mean {:.1}, interior {:.1}% of {} cells",
+        min,
+        max,
+        mean,
+        interior,
+        its.len()
+    );
+    if interior > 80.0 {
+        out.push_str("\nhint: mostly interior; lower iters or zoom out");
+    } else if share(late, escaped) > 5.0 {
+        out.push_str(&format!(
+            "\nhint: {:.1}% of escapes come in the last tenth of iters; raise iters for more detail",
+            share(late, escaped)
+        ));
+    }
+    out
+}
+// Percentage of cells that reached max_iter.
+pub fn interior_share(its: &[usize], max_iter: usize) -> f64 {
+    100.0 * its.iter().filter(|&&it| it >= max_iter).count() as f64 / its.len().max(1) as f64
+}
+// sweep=iters:start,stop,step: the interior share at each budget, with its change from
+// the previous one, on stderr. Leaves cfg.iters at stop and returns that buffer.
+fn run_sweep(cfg: &mut Config, [start, stop, step]: [usize; 3]) -> Vec<usize> {
+    let mut last = None;
+    cfg.iters = start;
+    loop {
+        let its = compute_iterations(cfg);
+        let share = interior_share(&its, cfg.iters);
+        match last {
+            Some(prev) => eprintln!(
+                "sweep: iters={} interior {:.2}% ({:+.2})",
+                cfg.iters,
+                share,
+                share - prev
+            ),
+            None => eprintln!("sweep: iters={} interior {:.2}%", cfg.iters, share),
+        }
+        if cfg.iters == stop {
+            return its;
+        }
+        last = Some(share);
+        cfg.iters = (cfg.iters + step).min(stop); // stop is always the last step
+    }
+}
+// Palette position per escape count in the frame, as (count, the fraction of escaped
+// cells with a lower count) in count order. Only counts that occur get an entry, so
+// the size follows the image rather than max_iter.
+pub fn histogram_ranks(its: &[usize], max_iter: usize) -> Vec<(usize, f64)> {
+    let mut counts: Vec<usize> = its.iter().map(|&it| it.min(max_iter)).collect();
+    counts.sort_unstable();
+    let escaped = counts.partition_point(|&it| it < max_iter).max(1);
+    let mut ranks: Vec<(usize, f64)> = Vec::new();
+    for (below, &it) in counts.iter().enumerate() {
+        if ranks.last().map(|&(c, _)| c) != Some(it) {
+            ranks.push((it, below as f64 / escaped as f64));
+        }
+    }
+    ranks
+}
+// The histogram_ranks() entry for count it.
+fn histogram_rank(ranks: &[(usize, f64)], it: usize) -> f64 {
+    let i = ranks.partition_point(|&(c, _)| c < it);
+    ranks.get(i).map_or(1.0, |&(_, t)| t)
+}
+pub fn hsv_to_rgb(h: f64, s: f64, v: f64) -> [u8; 3] {
+    let c = v * s;
+    let hp = (h.rem_euclid(360.0)) / 60.0;
+    let x = c * (1.0 - (hp % 2.0 - 1.0).abs());
+    let (r, g, b) = match hp as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    [r, g, b].map(|ch| ((ch + m) * 255.0).round() as u8)
+}
+// color=truecolor: hue sweeps from blue through the spectrum as the count rises.
+// Cells take smooth_position() in its place so the sweep has no steps.
+fn truecolor_cell(it: usize, cfg: &Config) -> String {
+    truecolor_at(it as f64 / cfg.iters as f64, cfg)
+}
+// A 24-bit block for palette position t: along the gradient stops when there are
+// some, otherwise the color=truecolor hue ramp.
+fn truecolor_at(t: f64, cfg: &Config) -> String {
+    let rgb = match &cfg.palette {
+        Palette::Gradient(stops) => gradient_rgb(stops, if cfg.invert { 1.0 - t } else { t }),
+        _ => hue_ramp(t),
+    };
+    rgb_cell(rgb, cfg.gamma)
+}
+fn hue_ramp(t: f64) -> Rgb {
+    hsv_to_rgb(240.0 + 360.0 * t, 0.85, 1.0)
+}
+// A full block in a 24-bit foreground color.
+fn rgb_cell(rgb: Rgb, gamma: f64) -> String {
+    let [r, g, b] = apply_gamma(rgb, gamma);
+    format!("\x1b[38;2;{};{};{}m\u{2588}\x1b[0m", r, g, b)
+}
+// Linear blend between the two stops around t, the stops evenly spaced over [0, 1].
+pub fn gradient_rgb(stops: &[(u8, u8, u8)], t: f64) -> [u8; 3] {
+    let pos = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+    let k = (pos as usize).min(stops.len() - 2);
+    let (a, b, f) = (stops[k], stops[k + 1], pos - k as f64);
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * f).round() as u8;
+    [mix(a.0, b.0), mix(a.1, b.1), mix(a.2, b.2)]
+}
+// channel = (channel / 255)^(1 / gamma) * 255; exact no-op at gamma 1.
+pub fn apply_gamma(rgb: [u8; 3], gamma: f64) -> [u8; 3] {
+    if gamma == 1.0 {
+        return rgb;
+    }
+    rgb.map(|c| ((c as f64 / 255.0).powf(1.0 / gamma) * 255.0).round() as u8)
+}
+// Whether the terminal output carries 24-bit color escapes.
+fn uses_truecolor(cfg: &Config) -> bool {
+    cfg.color == ColorMode::Truecolor
+        || cfg.format == Format::Halfblock
+        || matches!(cfg.palette, Palette::Gradient(_))
+}
+// COLORTERM is the de facto way terminals advertise 24-bit color.
+fn truecolor_supported() -> bool {
+    matches!(
+        env::var("COLORTERM").as_deref(),
+        Ok("truecolor") | Ok("24bit")
+    )
+}
+// Frame cell (x, y) as a cell of the grid, or None when region= leaves it out.
+fn grid_cell(cfg: &Config, (x, y): (usize, usize)) -> Option<(usize, usize)> {
+    let ((x0, y0), (w, h)) = (cfg.region_origin(), cfg.grid());
+    (x >= x0 && y >= y0 && x - x0 < w && y - y0 < h).then(|| (x - x0, y - y0))
+}
+// Overlay glyphs for row y (axes, origin, crosshair), None where the fractal shows through.
+fn overlay_row(cfg: &Config, y: usize, w: usize) -> Vec<Option<char>> {
+    let mut marks = vec![None; w];
+    if cfg.show_axes {
+        // located in the frame: region= may leave out the view center but still show an axis
+        let real = frame_cell(cfg, cfg.center_x, 0.0);
+        let imag = frame_cell(cfg, 0.0, cfg.center_y);
+        let (x0, y0) = cfg.region_origin();
+        // '-' is whichever axis runs along a row; transposed that is the imaginary one
+        let (across, down) = if cfg.transpose {
+            (imag, real)
+        } else {
+            (real, imag)
+        };
+        if let Some((_, ay)) = across {
+            if ay >= y0 && ay - y0 == y {
+                marks.iter_mut().for_each(|m| *m = Some('-'));
+            }
+        }
+        if let Some((ax, _)) = down {
+            if ax >= x0 && ax - x0 < w {
+                let m = &mut marks[ax - x0];
+                *m = Some(if m.is_some() { '+' } else { '|' });
+            }
+        }
+    }
+    if cfg.show_origin {
+        if let Some((ox, oy)) = complex_to_cell(cfg, 0.0, 0.0) {
+            if oy == y {
+                marks[ox] = Some('+');
+            }
+        }
+    }
+    if cfg.crosshair {
+        // the center falls between two cells on even sizes; take the lower one
+        let (fw, fh) = cfg.frame();
+        let center = ((fw.max(1) - 1) / 2, (fh.max(1) - 1) / 2);
+        if let Some((cx, cy)) = grid_cell(cfg, center) {
+            if cy == y && cx < w {
+                marks[cx] = Some('+');
+            }
+        }
+    }
+    marks
+}
+// Shade one row of escape counts, then apply the overlays that touch it.
+// ranks come from histogram_ranks() and are only used in color=histogram.
+pub fn render_row(cfg: &Config, y: usize, its: &[usize], ranks: Option<&[(usize, f64)]>) -> String {
+    render_row_with(cfg, y, its, ranks, None)
+}
+// render_row(), with map (when given) coloring every cell in place of the palette.
+fn render_row_with(
+    cfg: &Config,
+    y: usize,
+    its: &[usize],
+    ranks: Option<&[(usize, f64)]>,
+    map: Option<&dyn ColorMap>,
+) -> String {
+    let distance =
+        cfg.color == ColorMode::Distance && cfg.mode == Mode::Mandelbrot && cfg.power == 2;
+    let trap = match cfg.color {
+        ColorMode::Trap(t) => Some(t),
+        _ => None,
+    };
+    let marks = overlay_row(cfg, y, its.len());
+    let mut out = String::with_capacity(its.len());
+    for (x, &it) in its.iter().enumerate() {
+        if let Some(c) = marks[x] {
+            out.push(c);
+            continue;
+        }
+        let threshold = if cfg.dither {
+            dither_threshold(cfg, x, y)
+        } else {
+            0.5
+        };
+        match (ranks, trap) {
+            _ if it == SKIPPED => out.push(SKIPPED_GLYPH),
+            _ if cfg.mode == Mode::Newton => {
+                let (u, v) = cell_to_complex(cfg, x, y);
+                out.push(newton_glyph(cfg, newton_root(u, v, cfg.iters)))
+            }
+            _ if cfg.color == ColorMode::Truecolor && map.is_none() && it < cfg.iters => {
+                out.push_str(&truecolor_at(smooth_position(cfg, x, y, it), cfg))
+            }
+            _ if cfg.color == ColorMode::Truecolor && map.is_none() => {
+                out.push_str(&interior_cell(cfg))
+            }
+            _ if distance => push_shade(
+                &mut out,
+                it,
+                distance_position(cfg, x, y),
+                threshold,
+                cfg,
+                map,
+            ),
+            _ if cfg.color == ColorMode::Angle && it < cfg.iters => {
+                push_shade(&mut out, it, angle_position(cfg, x, y), threshold, cfg, map)
+            }
+            // shaded as escaped, so the interior takes palette glyphs too
+            _ if cfg.color == ColorMode::Interior && it >= cfg.iters => push_shade(
+                &mut out,
+                0,
+                interior_position(cfg, x, y),
+                threshold,
+                cfg,
+                map,
+            ),
+            // the trap colors the interior too, so shade every cell as escaped
+            (_, Some(t)) => push_shade(
+                &mut out,
+                0,
+                trap_position(cfg, x, y, t),
+                threshold,
+                cfg,
+                map,
+            ),
+            (Some(r), _) if cfg.color == ColorMode::Histogram => push_shade(
+                &mut out,
+                it,
+                histogram_rank(r, it.min(cfg.iters)),
+                threshold,
+                cfg,
+                map,
+            ),
+            _ => {
+                let t = match cfg.palette {
+                    Palette::Gradient(_) => smooth_position(cfg, x, y, it),
+                    _ => it as f64 / cfg.iters as f64,
+                };
+                push_shade(&mut out, it, t, threshold, cfg, map)
+            }
+        }
+    }
+    out
+}
+// shade_at() for glyph palettes; a gradient palette draws a truecolor block instead,
+// leaving the interior to interior_cell() as color=truecolor does. A ColorMap colors every cell.
+fn push_shade(
+    out: &mut String,
+    it: usize,
+    t: f64,
+    threshold: f64,
+    cfg: &Config,
+    map: Option<&dyn ColorMap>,
+) {
+    match (map, &cfg.palette) {
+        (Some(map), _) => out.push_str(&rgb_cell(map.map(it, cfg.iters, t), cfg.gamma)),
+        (None, Palette::Gradient(_)) if it < cfg.iters => out.push_str(&truecolor_at(t, cfg)),
+        (None, Palette::Gradient(_)) => out.push_str(&interior_cell(cfg)),
+        _ => out.push(shade_at(it, t, threshold, cfg)),
+    }
+}
+// dither: the rounding threshold for cell (x, y), taken at its place in the whole frame
+// so region= tiles line up.
+fn dither_threshold(cfg: &Config, x: usize, y: usize) -> f64 {
+    let (x0, y0) = cfg.region_origin();
+    (BAYER[(y + y0) % 4][(x + x0) % 4] as f64 + 0.5) / 16.0
+}
+pub fn render(cfg: Config) -> String {
+    let start = Instant::now();
+    render_iterations(
+        &cfg,
+        &parallel_rows(&cfg, false, |y| budget_row(&cfg, start, y)),
+    )
+}
+// One cell of an Image: the escape count (iters or more is the interior) and the
+// fractional count that smooths the steps between one count and the next.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub iter: usize,
+    pub smooth: f64,
+}
+// The sampled grid before any palette or overlay, row-major.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub iters: usize, // the max_iter the counts were taken with
+    pub cells: Vec<Cell>,
+}
+impl Image {
+    // Plain glyphs at palette position iter / iters, one line per row: what render()
+    // draws for a view with no other options. Overlays, color modes and the rest are
+    // left to the caller, as is any use of smooth.
+    pub fn to_ascii(&self, palette: &Palette) -> String {
+        let glyphs = palette.glyphs();
+        let last = glyphs.len() - 1;
+        let mut out = String::with_capacity((self.width + 1) * self.height);
+        for row in self.cells.chunks(self.width.max(1)) {
+            for cell in row {
+                let idx = if cell.iter >= self.iters {
+                    last
+                } else {
+                    (cell.iter as f64 / self.iters as f64 * last as f64 + 0.5) as usize
+                };
+                out.push(glyphs[idx]);
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+// Escape data for the view, for library callers that color or export it themselves.
+// The counts are the ones render() shades.
+pub fn render_grid(cfg: Config) -> Image {
+    let (width, height) = cfg.grid();
+    let cells = parallel_rows(&cfg, false, |y| {
+        let row = compute_row(&cfg, y);
+        row.iter()
+            .enumerate()
+            .map(|(x, &iter)| Cell {
+                iter,
+                smooth: smooth_count(&cfg, x, y, iter),
+            })
+            .collect()
+    });
+    Image {
+        width,
+        height,
+        iters: cfg.iters,
+        cells,
+    }
+}
+// n + 1 - log_power(ln |z| / ln R) for an escaped cell, R the bailout= radius: how far
+// past it the last step went fills in between integer counts, 1 at |z| = R down to 0
+// at R^power.
+// Interior and Newton cells keep their count.
+fn smooth_count(cfg: &Config, x: usize, y: usize, it: usize) -> f64 {
+    if it >= cfg.iters || cfg.mode == Mode::Newton {
+        return it as f64;
+    }
+    let (n, zx, zy) = final_z(cfg, x, y);
+    if n >= cfg.iters {
+        return it as f64; // aa= or the bailout counted it as escaped where the center did not
+    }
+    let overshoot = (zx.hypot(zy).ln() / cfg.bailout.ln()).ln() / (cfg.power as f64).ln();
+    (n as f64 + 1.0 - overshoot).max(0.0)
+}
+// Palette position from smooth_count(), for the continuous ramps of color=truecolor and
+// palette=gradient; glyph palettes keep whole counts.
+fn smooth_position(cfg: &Config, x: usize, y: usize, it: usize) -> f64 {
+    (smooth_count(cfg, x, y, it) / cfg.iters as f64).clamp(0.0, 1.0)
+}
+// compare=: two renders side by side, the left one padded out to its widest line.
+pub fn side_by_side(left: &str, right: &str) -> String {
+    let (left, right): (Vec<&str>, Vec<&str>) = (left.lines().collect(), right.lines().collect());
+    let width = left.iter().map(|l| display_width(l)).max().unwrap_or(0);
+    let mut out = String::new();
+    for i in 0..left.len().max(right.len()) {
+        let l = left.get(i).copied().unwrap_or("");
+        let pad = " ".repeat(width - display_width(l));
+        out.push_str(&format!(
+            "{}{} | {}\n",
+            l,
+            pad,
+            right.get(i).copied().unwrap_or("")
+        ));
+    }
+    out
+}
+// Printed width of a line: the chars outside ANSI escape sequences.
+fn display_width(line: &str) -> usize {
+    let mut escape = false;
+    let mut width = 0;
+    for c in line.chars() {
+        match c {
+            '\x1b' => escape = true,
+            _ if escape => escape = !c.is_ascii_alphabetic(),
+            _ => width += 1,
+        }
+    }
+    width
+}
+// compare= --diff: '#' where the two escape-count buffers differ, ' ' where they agree.
+pub fn diff_map(cfg: &Config, other: &Config, a: &[usize], b: &[usize]) -> String {
+    let w = cfg.grid().0.max(1);
+    let mut out = String::with_capacity(a.len() + a.len() / w);
+    for (ra, rb) in a.chunks(w).zip(b.chunks(w)) {
+        out.extend(ra.iter().zip(rb).map(|(&x, &y)| {
+            if counts_differ(cfg, other, x, y) {
+                '#'
+            } else {
+                ' '
+            }
+        }));
+        out.push('\n');
+    }
+    out
+}
+// Interior in both views counts as agreeing, whatever each one's iters is.
+fn counts_differ(cfg: &Config, other: &Config, a: usize, b: usize) -> bool {
+    a != b && !(a >= cfg.iters && b >= other.iters)
+}
+// z0 = 0, z1, ... under z^power + c: iters steps, or up to the first point past the bailout.
+pub fn orbit_points(cfg: &Config, cx: f64, cy: f64) -> Vec<(f64, f64)> {
+    let (mut x, mut y) = (0.0, 0.0);
+    let mut points = vec![(x, y)];
+    while points.len() <= cfg.iters && x * x + y * y <= cfg.bailout * cfg.bailout {
+        let (mut px, mut py) = (x, y);
+        for _ in 1..cfg.power {
+            (px, py) = (px * x - py * y, px * y + py * x);
+        }
+        (x, y) = (px + cx, py + cy);
+        points.push((x, y));
+    }
+    points
+}
+// orbit=: a blank view (overlays still drawn) with each on-screen orbit point as its
+// step number mod 10. A cell keeps the first step that lands on it, so where an orbit
+// settles into a cycle the digits show how it got there.
+pub fn render_orbit(cfg: &Config, points: &[(f64, f64)]) -> String {
+    let (w, h) = cfg.grid();
+    let mut cells: Vec<Vec<char>> = (0..h)
+        .map(|y| {
+            overlay_row(cfg, y, w)
+                .iter()
+                .map(|m| m.unwrap_or(' '))
+                .collect()
+        })
+        .collect();
+    let mut seen = vec![false; w * h];
+    for (step, &(u, v)) in points.iter().enumerate() {
+        if let Some((x, y)) = complex_to_cell(cfg, u, v) {
+            if !std::mem::replace(&mut seen[y * w + x], true) {
+                cells[y][x] = char::from_digit(step as u32 % 10, 10).unwrap();
+            }
+        }
+    }
+    cells
+        .into_iter()
+        .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+        .collect()
+}
+// render() in 24-bit blocks colored by map at each cell's palette position, which
+// still follows cfg.color; overlays and color=newton keep their glyphs.
+pub fn render_with(cfg: &Config, map: &dyn ColorMap) -> String {
+    shade_rows(cfg, &compute_iterations(cfg), Some(map))
+}
+// Shade a buffer from compute_iterations(); re-coloring a view only needs this step.
+pub fn render_iterations(cfg: &Config, its: &[usize]) -> String {
+    shade_rows(cfg, its, None)
+}
+fn shade_rows(cfg: &Config, its: &[usize], map: Option<&dyn ColorMap>) -> String {
+    let (w, h) = cfg.grid();
+    let ranks = (cfg.color == ColorMode::Histogram).then(|| histogram_ranks(its, cfg.iters));
+    let mut out = String::with_capacity((w + 1) * h);
+    let margins = ruler_margins(cfg);
+    if cfg.ruler {
+        out.push_str(&ruler_top(cfg, &margins));
+    }
+    for (y, row) in its.chunks(w.max(1)).enumerate() {
+        if let Some(margin) = margins.get(y) {
+            out.push_str(margin);
+        }
+        out.push_str(&render_row_with(cfg, y, row, ranks.as_deref(), map));
+        out.push('\n');
+    }
+    if cfg.legend {
+        out.push_str(&legend(cfg));
+        out.push('\n');
+    }
+    out
+}
+// --ruler: the plane coordinate along columns and along rows (real and imaginary,
+// swapped by --transpose), from the same cell_to_complex as the render.
+fn column_value(cfg: &Config, x: usize) -> f64 {
+    let (u, v) = cell_to_complex(cfg, x, 0);
+    if cfg.transpose {
+        v
+    } else {
+        u
+    }
+}
+fn row_value(cfg: &Config, y: usize) -> f64 {
+    let (u, v) = cell_to_complex(cfg, 0, y);
+    if cfg.transpose {
+        u
+    } else {
+        v
+    }
+}
+// Enough decimals to tell labels step apart. A single row or column has no step, so
+// the view's scale stands in for it.
+fn ruler_decimals(cfg: &Config, step: f64) -> usize {
+    let step = if step != 0.0 && step.is_finite() {
+        step.abs()
+    } else {
+        cfg.scale
+    };
+    (-step.log10()).ceil().clamp(0.0, 14.0) as usize + 1
+}
+// Labelled columns and rows sit on multiples of RULER_COLS/RULER_ROWS of the whole
+// frame, so region= tiles share their ticks.
+fn ruler_ticks(n: usize, origin: usize, every: usize) -> impl Iterator<Item = usize> {
+    (0..n).filter(move |i| (i + origin).is_multiple_of(every))
+}
+// --ruler: the left margin of every row, its label and '-' on labelled rows, else '|';
+// empty without --ruler. Built once per render, since the width depends on all labels.
+fn ruler_margins(cfg: &Config) -> Vec<String> {
+    let h = cfg.grid().1;
+    if !cfg.ruler {
+        return Vec::new();
+    }
+    let d = ruler_decimals(cfg, row_value(cfg, RULER_ROWS) - row_value(cfg, 0));
+    let mut labels = vec![None; h];
+    for y in ruler_ticks(h, cfg.region_origin().1, RULER_ROWS) {
+        labels[y] = Some(format!("{:.*}", d, row_value(cfg, y)));
+    }
+    let width = labels.iter().flatten().map(|l| l.len()).max().unwrap_or(0);
+    let margin = |label: &Option<String>| match label {
+        Some(label) => format!("{:>w$} -", label, w = width),
+        None => format!("{:w$} |", "", w = width),
+    };
+    labels.iter().map(margin).collect()
+}
+// --ruler: two lines over the image, column labels above '|' ticks, past the left
+// margins. A label that would run into the next one or off the edge is left out.
+fn ruler_top(cfg: &Config, margins: &[String]) -> String {
+    let w = cfg.grid().0;
+    let margin = margins.first().map_or(0, |m| m.len()); // every margin has the same width
+    let d = ruler_decimals(cfg, column_value(cfg, RULER_COLS) - column_value(cfg, 0));
+    let (mut labels, mut ticks) = (" ".repeat(margin), vec![' '; w]);
+    for x in ruler_ticks(w, cfg.region_origin().0, RULER_COLS) {
+        let label = format!("{:.*}", d, column_value(cfg, x));
+        if labels.len() <= margin + x && x + label.len() <= w {
+            labels.push_str(&" ".repeat(margin + x - labels.len()));
+            labels.push_str(&label);
+            ticks[x] = '|';
+        }
+    }
+    format!(
+        "{}\n{}{}\n",
+        labels,
+        " ".repeat(margin),
+        ticks.iter().collect::<String>()
+    )
+}
+// --legend: the active ramp from the interior glyph out to the fastest escapes.
+pub fn legend(cfg: &Config) -> String {
+    let ramp: String = match cfg.color {
+        _ if cfg.color == ColorMode::Truecolor || matches!(cfg.palette, Palette::Gradient(_)) => {
+            let n = PALETTE.len();
+            let blocks = (0..n - 1)
+                .rev()
+                .map(|k| truecolor_cell(k * cfg.iters / (n - 1), cfg));
+            std::iter::once(interior_cell(cfg)).chain(blocks).collect()
+        }
+        _ if cfg.palette == Palette::Parity => {
+            let interior = interior_glyph(cfg);
+            let (even, odd) = (
+                PARITY_GLYPHS[cfg.invert as usize],
+                PARITY_GLYPHS[!cfg.invert as usize],
+            );
+            return format!(
+                "[interior] {} [odd] {} [even] {} [escaped]",
+                interior, odd, even
+            );
+        }
+        // interior first, then the escaped glyphs
+        _ => {
+            let glyphs = cfg.palette.glyphs();
+            let escaped: Vec<char> = match cfg.invert {
+                true => glyphs[1..].to_vec(),
+                false => glyphs[..glyphs.len() - 1].iter().rev().copied().collect(),
+            };
+            std::iter::once(interior_glyph(cfg))
+                .chain(escaped)
+                .collect()
+        }
+    };
+    format!("[interior] {} [escaped]", ramp)
+}
+// Same bytes as render(), but rows are written as they finish: one at a time, or with
+// threads=N only the rows done ahead of the next one to print are held.
+pub fn render_streaming<W: Write>(cfg: &Config, out: &mut W) -> io::Result<()> {
+    if cfg.color == ColorMode::Histogram {
+        // ranks depend on the whole frame
+        out.write_all(render(cfg.clone()).as_bytes())?;
+        return out.flush();
+    }
+    let margins = ruler_margins(cfg);
+    if cfg.ruler {
+        out.write_all(ruler_top(cfg, &margins).as_bytes())?;
+    }
+    let start = Instant::now();
+    let row = |y: usize| {
+        let mut line = margins.get(y).cloned().unwrap_or_default();
+        line.push_str(&render_row(cfg, y, &budget_row(cfg, start, y), None));
+        line.push('\n');
+        vec![line]
+    };
+    let mut y = 0;
+    each_row(cfg, false, row, |lines| {
+        for line in lines {
+            out.write_all(line.as_bytes())?;
+        }
+        y += 1;
+        if y % 16 == 0 {
+            out.flush()?;
+        }
+        Ok(())
+    })?;
+    if cfg.legend {
+        writeln!(out, "{}", legend(cfg))?;
+    }
+    out.flush()
+}
+// render=halfblock: the view sampled on a w x 2h grid of image colors, each pair of
+// rows drawn as '\u{2580}' cells with the top pixel as foreground and the bottom one
+// as background. Escape codes are only repeated when a cell's colors change.
+pub fn render_halfblock(cfg: &Config) -> String {
+    let mut px = cfg.clone();
+    px.height = 2 * cfg.height;
+    px.region = cfg.region.map(|[x0, y0, x1, y1]| [x0, 2 * y0, x1, 2 * y1]);
+    let pixels = compute_colors(&px);
+    let (w, h) = px.grid();
+    let mut out = String::new();
+    for y in (0..h).step_by(2) {
+        let mut last = None;
+        for x in 0..w {
+            // an odd preview grid leaves the last bottom half black
+            let pair = (
+                pixels[y * w + x],
+                if y + 1 < h {
+                    pixels[(y + 1) * w + x]
+                } else {
+                    [0; 3]
+                },
+            );
+            if last != Some(pair) {
+                let ([r, g, b], [br, bg, bb]) = pair;
+                out.push_str(&format!(
+                    "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m",
+                    r, g, b, br, bg, bb
+                ));
+                last = Some(pair);
+            }
+            out.push('\u{2580}');
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+pub fn write_csv<W: Write>(out: &mut W, cfg: &Config, its: &[usize]) -> io::Result<()> {
+    writeln!(out, "# {}", status_line(cfg))?;
+    write_matrix(out, cfg, its)
+}
+// The bare count matrix: one line per row, interior cells as max_iter itself.
+pub fn write_matrix<W: Write>(out: &mut W, cfg: &Config, its: &[usize]) -> io::Result<()> {
+    for row in its.chunks(cfg.grid().0.max(1)) {
+        let fields: Vec<String> = row.iter().map(|it| it.to_string()).collect();
+        writeln!(out, "{}", fields.join(","))?;
+    }
+    out.flush()
+}
+// Columns of each printable ASCII glyph, ' ' to '~'; bit 0 is the top row.
+const FONT_5X7: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5F, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00], // space ! "
+    [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62], // # $ %
+    [0x36, 0x49, 0x55, 0x22, 0x50],
+    [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // & ' (
+    [0x00, 0x41, 0x22, 0x1C, 0x00],
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08],
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // ) * +
+    [0x00, 0x50, 0x30, 0x00, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00], // , - .
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E],
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // / 0 1
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // 2 3 4
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 5 6 7
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1E],
+    [0x00, 0x36, 0x36, 0x00, 0x00], // 8 9 :
+    [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00],
+    [0x14, 0x14, 0x14, 0x14, 0x14], // ; < =
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // > ? @
+    [0x7E, 0x11, 0x11, 0x11, 0x7E],
+    [0x7F, 0x49, 0x49, 0x49, 0x36],
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // A B C
+    [0x7F, 0x41, 0x41, 0x22, 0x1C],
+    [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x01, 0x01], // D E F
+    [0x3E, 0x41, 0x41, 0x51, 0x32],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F],
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // G H I
+    [0x20, 0x40, 0x41, 0x3F, 0x01],
+    [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // J K L
+    [0x7F, 0x02, 0x04, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F],
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // M N O
+    [0x7F, 0x09, 0x09, 0x09, 0x06],
+    [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // P Q R
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01],
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // S T U
+    [0x1F, 0x20, 0x40, 0x20, 0x1F],
+    [0x7F, 0x20, 0x18, 0x20, 0x7F],
+    [0x63, 0x14, 0x08, 0x14, 0x63], // V W X
+    [0x03, 0x04, 0x78, 0x04, 0x03],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // Y Z [
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x7F, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04], // \ ] ^
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00],
+    [0x20, 0x54, 0x54, 0x54, 0x78], // _ ` a
+    [0x7F, 0x48, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // b c d
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7E, 0x09, 0x01, 0x02],
+    [0x08, 0x54, 0x54, 0x54, 0x3C], // e f g
+    [0x7F, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7D, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // h i j
+    [0x7F, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7F, 0x40, 0x00],
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // k l m
+    [0x7C, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // n o p
+    [0x08, 0x14, 0x14, 0x18, 0x7C],
+    [0x7C, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20], // q r s
+    [0x04, 0x3F, 0x44, 0x40, 0x20],
+    [0x3C, 0x40, 0x40, 0x20, 0x7C],
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // t u v
+    [0x3C, 0x40, 0x30, 0x40, 0x3C],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // w x y
+    [0x44, 0x64, 0x54, 0x4C, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // z { |
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x02, 0x01, 0x02, 0x04, 0x02], // } ~
+];
+// compute_colors() plus the caption, if any: what the image writers get.
+fn image_pixels(cfg: &Config) -> Vec<[u8; 3]> {
+    let mut pixels = compute_colors(cfg);
+    if let Some(text) = &cfg.caption {
+        if !draw_caption(cfg, &mut pixels, text) {
+            eprintln!("warning: image too short for a caption; left it off");
+        }
+    }
+    pixels
+}
+// The text in FONT_5X7 on a darkened band along the bottom, centered and cut to the
+// width; other characters show as '?'. false, leaving the image alone, when the band
+// would cover more than a third of it.
+fn draw_caption(cfg: &Config, pixels: &mut [[u8; 3]], text: &str) -> bool {
+    let (w, h) = cfg.grid();
+    let k = (h / CAPTION_SHARE / 9).max(1); // pixels per font dot
+    let band = 9 * k; // 7 dot rows with a blank one above and below
+    if band * 3 > h {
+        return false;
+    }
+    let fits = (w / (6 * k)).min(text.chars().count());
+    let x0 = (w - (fits * 6 * k).saturating_sub(k)) / 2; // no gap after the last glyph
+    pixels[(h - band) * w..]
+        .iter_mut()
+        .for_each(|p| *p = p.map(|c| c / 4));
+    for (i, ch) in text.chars().take(fits).enumerate() {
+        let ch = if (' '..='~').contains(&ch) { ch } else { '?' };
+        for (col, bits) in FONT_5X7[ch as usize - 32].iter().enumerate() {
+            for row in (0..7).filter(|r| bits >> r & 1 == 1) {
+                let (x, y) = (x0 + (6 * i + col) * k, h - band + (row + 1) * k);
+                for dy in 0..k {
+                    pixels[(y + dy) * w + x..][..k].fill([255; 3]);
+                }
+            }
+        }
+    }
+    true
+}
+// Image pixel color: navy for fast escapes up to white near the boundary; the interior is black.
+fn image_color(it: usize, max_iter: usize) -> [u8; 3] {
+    if it >= max_iter {
+        return [0, 0, 0];
+    }
+    let t = it as f64 / max_iter as f64;
+    let c = (t * 255.0).round() as u8;
+    [c, c, (96.0 + t * 159.0).round() as u8]
+}
+// The built-in color maps: image output's ramp, color=truecolor's hue sweep, and the
+// palettes themselves, glyphs as gray by how much ink they put down.
+pub struct ImageRamp;
+pub struct HueRamp;
+impl ColorMap for ImageRamp {
+    fn map(&self, it: usize, max: usize, _smooth: f64) -> Rgb {
+        image_color(it, max)
+    }
+}
+impl ColorMap for HueRamp {
+    fn map(&self, it: usize, max: usize, smooth: f64) -> Rgb {
+        if it >= max {
+            [0; 3]
+        } else {
+            hue_ramp(smooth)
+        }
+    }
+}
+impl ColorMap for Palette {
+    fn map(&self, it: usize, max: usize, smooth: f64) -> Rgb {
+        let last = self.glyphs().len() - 1;
+        match self {
+            Palette::Gradient(_) if it >= max => [0; 3], // blank, as on the terminal
+            Palette::Gradient(stops) => gradient_rgb(stops, smooth),
+            _ if it >= max => [255; 3], // the densest glyph
+            Palette::Parity => [[96; 3], [255; 3]][it % 2],
+            _ => [((smooth * last as f64).round() as usize * 255 / last) as u8; 3],
+        }
+    }
+}
+// Binary P6 image, one pixel per cell of the sampled grid.
+pub fn write_ppm<W: Write>(out: &mut W, cfg: &Config, pixels: &[[u8; 3]]) -> io::Result<()> {
+    let (w, h) = cfg.grid();
+    write!(out, "P6\n{} {}\n255\n", w, h)?;
+    for px in pixels {
+        out.write_all(px)?;
+    }
+    out.flush()
+}
+// Image pixels, row-major; with aa > 1 the subsample colors are averaged.
+pub fn compute_colors(cfg: &Config) -> Vec<Rgb> {
+    compute_colors_with(cfg, &ImageRamp)
+}
+// compute_colors() with escape counts colored by map instead of the image ramp.
+pub fn compute_colors_with(cfg: &Config, map: &dyn ColorMap) -> Vec<Rgb> {
+    if let Some(path) = &cfg.checkpoint {
+        return checkpointed(cfg, path, |band| compute_colors_with(band, map));
+    }
+    parallel_rows(cfg, false, |y| {
+        (0..cfg.grid().0)
+            .map(|x| {
+                let s: Vec<[u8; 3]> = match cfg.mode {
+                    Mode::Newton => sample_points(cfg, x, y)
+                        .into_iter()
+                        .map(|(u, v)| newton_color(newton_root(u, v, cfg.iters)))
+                        .collect(),
+                    _ => cell_samples(cfg, x, y)
+                        .into_iter()
+                        .map(|it| match cfg.interior_color {
+                            Some(rgb) if it >= cfg.iters => rgb,
+                            _ => map.map(it, cfg.iters, it as f64 / cfg.iters as f64),
+                        })
+                        .collect(),
+                };
+                let mut sum = [0usize; 3];
+                for c in &s {
+                    (0..3).for_each(|i| sum[i] += c[i] as usize);
+                }
+                apply_gamma(sum.map(|c| ((c + s.len() / 2) / s.len()) as u8), cfg.gamma)
+            })
+            .collect()
+    })
+}
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+fn adler32(bytes: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &x in bytes {
+        a = (a + x as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+fn png_chunk<W: Write>(out: &mut W, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    out.write_all(&(data.len() as u32).to_be_bytes())?;
+    out.write_all(kind)?;
+    out.write_all(data)?;
+    let crc = crc32(&[&kind[..], data].concat());
+    out.write_all(&crc.to_be_bytes())
+}
+// 8-bit RGB PNG in a single IDAT of stored (uncompressed) deflate blocks.
+pub fn write_png<W: Write>(out: &mut W, cfg: &Config, pixels: &[[u8; 3]]) -> io::Result<()> {
+    let (w, h) = cfg.grid();
+    let mut raw = Vec::with_capacity((w * 3 + 1) * h);
+    for row in pixels.chunks(w.max(1)) {
+        raw.push(0); // filter type None
+        row.iter().for_each(|px| raw.extend_from_slice(px));
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(0xFFFF).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]); // empty final block
+    }
+    while let Some(block) = blocks.next() {
+        let len = block.len() as u16;
+        zlib.push(blocks.peek().is_none() as u8); // BFINAL, BTYPE=00 (stored)
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(w as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(h as u32).to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8-bit RGB, deflate, no filter, no interlace
+    out.write_all(b"\x89PNG\r\n\x1a\n")?;
+    png_chunk(out, b"IHDR", &ihdr)?;
+    png_chunk(out, b"IDAT", &zlib)?;
+    png_chunk(out, b"IEND", &[])?;
+    out.flush()
+}
+fn html_escape(c: char) -> String {
+    match c {
+        '&' => "&amp;".into(),
+        '<' => "&lt;".into(),
+        '>' => "&gt;".into(),
+        '"' => "&quot;".into(),
+        _ => c.to_string(),
+    }
+}
+// Self-contained page: one <pre> per row, one colored block per cell.
+pub fn write_html<W: Write>(out: &mut W, cfg: &Config, pixels: &[[u8; 3]]) -> io::Result<()> {
+    let (w, _) = cfg.grid();
+    let cell = html_escape('\u{2588}');
+    writeln!(
+        out,
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>mandelbrot</title>"
+    )?;
+    writeln!(out, "<style>pre{{margin:0;line-height:1}}</style></head>")?;
+    writeln!(out, "<body style=\"background:#111\">")?;
+    for row in pixels.chunks(w.max(1)) {
+        write!(out, "<pre>")?;
+        for [r, g, b] in row {
+            write!(
+                out,
+                "<span style=\"color:#{:02x}{:02x}{:02x}\">{}</span>",
+                r, g, b, cell
+            )?;
+        }
+        writeln!(out, "</pre>")?;
+    }
+    writeln!(out, "</body></html>")?;
+    out.flush()
+}
+// One rect per run of equal-colored cells in a row, over a background of the
+// interior color so the body of the set costs nothing.
+pub fn write_svg<W: Write>(out: &mut W, cfg: &Config, pixels: &[[u8; 3]]) -> io::Result<()> {
+    let (w, h) = cfg.grid();
+    // the interior is usually the largest single color, so it goes in the background
+    let bg = cfg
+        .interior_color
+        .map_or(image_color(cfg.iters, cfg.iters), |rgb| {
+            apply_gamma(rgb, cfg.gamma)
+        });
+    let hex = |[r, g, b]: [u8; 3]| format!("#{:02x}{:02x}{:02x}", r, g, b);
+    writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">", w, h)?;
+    writeln!(
+        out,
+        "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+        w,
+        h,
+        hex(bg)
+    )?;
+    for (y, row) in pixels.chunks(w.max(1)).enumerate() {
+        let mut x = 0;
+        while x < row.len() {
+            let run = row[x..].iter().take_while(|&&c| c == row[x]).count();
+            if row[x] != bg {
+                writeln!(
+                    out,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"1\" fill=\"{}\"/>",
+                    x,
+                    y,
+                    run,
+                    hex(row[x])
+                )?;
+            }
+            x += run;
+        }
+    }
+    writeln!(out, "</svg>")?;
+    out.flush()
+}
+// out=path, in the format its extension names, written to open() once that is known.
+fn write_image<W: Write>(
+    path: &str,
+    cfg: &Config,
+    open: impl FnOnce() -> io::Result<W>,
+) -> io::Result<()> {
+    if path.ends_with(".csv") {
+        let its = compute_iterations(cfg);
+        return write_matrix(&mut open()?, cfg, &its);
+    }
+    let write = match path.rsplit('.').next() {
+        Some("ppm") => write_ppm,
+        Some("png") => write_png,
+        Some("html") => write_html,
+        Some("svg") => write_svg,
+        _ => {
+            let msg = "unsupported output format (expected .ppm, .png, .html, .svg or .csv)";
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+    };
+    let pixels = image_pixels(cfg);
+    write(&mut open()?, cfg, &pixels)
+}
+// True once neighbouring cells are too close for f64 to tell apart at this center.
+pub fn precision_exhausted(cfg: &Config) -> bool {
+    let step = cfg.scale / cfg.frame().0.max(1) as f64;
+    step < PRECISION_LIMIT * cfg.center_x.abs().max(cfg.center_y.abs()).max(1.0)
+}
+fn status_line(cfg: &Config) -> String {
+    format!(
+        "w={} h={} cx={:.5} cy={:.5} scale={} depth {:.1} iters={}{} power={}{}{}{}{}",
+        cfg.width,
+        cfg.height,
+        cfg.center_x,
+        cfg.center_y,
+        cfg.scale,
+        (3.0 / cfg.scale).log2(), // zoom depth in halvings of the default view
+        cfg.iters,
+        if cfg.autoiters { " (auto)" } else { "" },
+        cfg.power,
+        cfg.seed.map_or(String::new(), |s| format!(" seed={}", s)),
+        if cfg.scale >= MAX_SCALE {
+            " (max scale)"
+        } else {
+            ""
+        },
+        if precision_exhausted(cfg) {
+            " (precision limit reached \u{2014} further zoom will not resolve detail)"
+        } else {
+            ""
+        },
+        if uses_truecolor(cfg) && !truecolor_supported() {
+            " (warning: COLORTERM does not advertise truecolor)"
+        } else {
+            ""
+        }
+    )
+}
+// cycle=on: iterate once, then redraw in place, stepping palette_offset each frame.
+fn cycle_palette(cfg: &Config) -> io::Result<()> {
+    let its = compute_iterations(cfg);
+    let mut frame = cfg.clone();
+    let mut out = io::stdout().lock();
+    catch_sigint();
+    write!(out, "\x1b[?25l\x1b[2J")?; // hide the cursor, clear
+    while !INTERRUPTED.load(Ordering::Relaxed) {
+        write!(out, "\x1b[H{}", render_iterations(&frame, &its))?;
+        out.flush()?;
+        frame.palette_offset += 1;
+        thread::sleep(CYCLE_FRAME);
+    }
+    writeln!(out, "\x1b[?25h")?;
+    out.flush()
+}
+// Shrink the view about its center; bounds keep their own proportions.
+fn zoom(cfg: &mut Config, factor: f64) {
+    let before = autoiters_extra(cfg.scale);
+    let (cx, cy) = (cfg.center_x, cfg.center_y);
+    match &mut cfg.bounds {
+        // one factor for both sides, held so that each stays within the scale= limits
+        Some(b) => {
+            let (w, h) = (b[1] - b[0], b[3] - b[2]);
+            let factor = factor.clamp(MIN_SCALE / w.min(h), MAX_SCALE / w.max(h));
+            let (hw, hh) = (w / 2.0 * factor, h / 2.0 * factor);
+            *b = [cx - hw, cx + hw, cy - hh, cy + hh];
+            cfg.scale = (2.0 * hw).clamp(MIN_SCALE, MAX_SCALE);
+        }
+        None => cfg.scale = (cfg.scale * factor).clamp(MIN_SCALE, MAX_SCALE),
+    }
+    if cfg.autoiters {
+        let iters = cfg
+            .iters
+            .saturating_add(autoiters_extra(cfg.scale))
+            .saturating_sub(before);
+        cfg.iters = iters.clamp(1, MAX_AUTOITERS.max(cfg.iters));
+    }
+}
+// Set the bounds, with center/scale describing the same region for the status line.
+// Each side must be within the scale= limits; the rectangle is drawn as given.
+fn set_bounds(cfg: &mut Config, [xmin, xmax, ymin, ymax]: [f64; 4]) -> Result<(), String> {
+    if xmax <= xmin || ymax <= ymin {
+        return Err("bounds need xmax > xmin and ymax > ymin".to_string());
+    }
+    for (axis, extent) in [("xmax - xmin", xmax - xmin), ("ymax - ymin", ymax - ymin)] {
+        if !(MIN_SCALE..=MAX_SCALE).contains(&extent) {
+            return Err(format!(
+                "bounds: {} is {:e}, outside the scale= limits {:e}..={}",
+                axis, extent, MIN_SCALE, MAX_SCALE
+            ));
+        }
+    }
+    cfg.bounds = Some([xmin, xmax, ymin, ymax]);
+    (cfg.center_x, cfg.center_y) = ((xmin + xmax) / 2.0, (ymin + ymax) / 2.0);
+    cfg.scale = xmax - xmin;
+    Ok(())
+}
+enum TourStep {
+    Pan(f64, f64), // in view widths
+    Zoom(f64),
+    Iters(usize),
+    Render,
+    Sleep(u64),
+}
+// The whole script is checked before anything is drawn.
+fn parse_tour(path: &str) -> Result<Vec<TourStep>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("tour file '{}': {}", path, e))?;
+    let mut steps = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let num = |i: usize| tour_arg::<f64>(&words, i);
+        let step = match words.first() {
+            None => continue,
+            Some(w) if w.starts_with('#') => continue,
+            Some(&"pan") if words.len() == 3 => {
+                num(1).and_then(|dx| Ok(TourStep::Pan(dx, num(2)?)))
+            }
+            Some(&"zoom") if words.len() == 2 => match num(1) {
+                Ok(f) if f > 0.0 => Ok(TourStep::Zoom(f)),
+                Ok(_) => Err("zoom factor must be positive".to_string()),
+                Err(e) => Err(e),
+            },
+            Some(&"iters") if words.len() == 2 => tour_arg(&words, 1).map(TourStep::Iters),
+            Some(&"render") if words.len() == 1 => Ok(TourStep::Render),
+            Some(&"sleep") if words.len() == 2 => tour_arg(&words, 1).map(TourStep::Sleep),
+            Some(_) => Err(format!(
+                "expected pan dx dy, zoom f, iters N, render or sleep ms, got '{}'",
+                line.trim()
+            )),
+        };
+        steps.push(step.map_err(|e| format!("{}:{}: {}", path, n + 1, e))?);
+    }
+    Ok(steps)
+}
+fn tour_arg<T: std::str::FromStr>(words: &[&str], i: usize) -> Result<T, String> {
+    words[i]
+        .parse()
+        .map_err(|_| format!("invalid number '{}'", words[i]))
+}
+// tour=path: apply the script's steps to cfg in order, printing a frame at each render.
+fn run_tour(cfg: &Config, path: &str) -> Result<(), String> {
+    let steps = parse_tour(path)?;
+    let mut view = cfg.clone();
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for step in steps {
+        match step {
+            TourStep::Pan(dx, dy) => {
+                let (du, dv) = (dx * view.scale, dy * view.scale);
+                (view.center_x, view.center_y) = (view.center_x + du, view.center_y + dv);
+                if let Some(b) = &mut view.bounds {
+                    *b = [b[0] + du, b[1] + du, b[2] + dv, b[3] + dv];
+                }
+            }
+            TourStep::Zoom(f) => zoom(&mut view, f),
+            TourStep::Iters(n) => view.iters = n,
+            TourStep::Render => {
+                render_streaming(&view, &mut out)
+                    .and_then(|_| writeln!(out))
+                    .map_err(|e| e.to_string())?;
+                if !view.quiet {
+                    eprintln!("{}", status_line(&view));
+                }
+            }
+            TourStep::Sleep(ms) => thread::sleep(Duration::from_millis(ms)),
+        }
+    }
+    Ok(())
+}
+// animate=between: view k of n on the way to cfg.zoom_to. Scale moves geometrically,
+// and the target's offset from the view center, measured in view widths, shrinks
+// linearly to zero, so it drifts across the screen at a steady pace.
+fn between_view(cfg: &Config, to: [f64; 3], k: usize, n: usize) -> (f64, f64, f64) {
+    let t = if n > 1 {
+        k as f64 / (n - 1) as f64
+    } else {
+        0.0
+    };
+    let scale = cfg.scale * (to[2] / cfg.scale).powf(t);
+    let pull = (1.0 - t) * scale / cfg.scale;
+    (
+        to[0] - (to[0] - cfg.center_x) * pull,
+        to[1] - (to[1] - cfg.center_y) * pull,
+        scale,
+    )
+}
+// animate=julia: one view per frame with c = (jx, jy) moved a step further along the
+// line to julia_to, from the start on the first frame to the target on the last.
+// The camera stays where cfg puts it.
+pub fn julia_sweep_configs(cfg: &Config) -> Vec<Config> {
+    let from = [cfg.julia_x, cfg.julia_y];
+    let to = cfg.julia_to.unwrap_or(from);
+    let n = cfg.frames;
+    (0..n)
+        .map(|k| {
+            let t = if n > 1 {
+                k as f64 / (n - 1) as f64
+            } else {
+                0.0
+            };
+            Config {
+                julia_x: from[0] + (to[0] - from[0]) * t,
+                julia_y: from[1] + (to[1] - from[1]) * t,
+                ..cfg.clone()
+            }
+        })
+        .collect()
+}
+// animate=: frame_0000.ppm, frame_0001.ppm, ... each zoom_rate deeper, stepping toward
+// zoom_to with animate=between, or moving c with animate=julia.
+fn write_frames(cfg: &Config) -> io::Result<()> {
+    let digits = (cfg.frames - 1).to_string().len().max(4);
+    let png = cfg.out.as_deref().is_some_and(|o| o.ends_with(".png"));
+    let sweep = cfg.julia_to.map(|_| julia_sweep_configs(cfg));
+    let mut frame = cfg.clone();
+    frame.bounds = frame.bounds.filter(|_| cfg.zoom_to.is_none()); // between moves by center/scale
+    for k in 0..cfg.frames {
+        if let Some(views) = &sweep {
+            frame = views[k].clone();
+        } else if let Some(to) = cfg.zoom_to {
+            let (cx, cy, scale) = between_view(cfg, to, k, cfg.frames);
+            let factor = scale / frame.scale;
+            zoom(&mut frame, factor); // keeps --autoiters in step
+            (frame.center_x, frame.center_y) = (cx, cy);
+        }
+        eprintln!("frame {}/{}", k + 1, cfg.frames);
+        let path = format!(
+            "frame_{:0w$}.{}",
+            k,
+            if png { "png" } else { "ppm" },
+            w = digits
+        );
+        let pixels = image_pixels(&frame);
+        let mut out = io::BufWriter::new(File::create(path)?);
+        if png {
+            write_png(&mut out, &frame, &pixels)?;
+        } else {
+            write_ppm(&mut out, &frame, &pixels)?;
+        }
+        if cfg.zoom_to.is_none() && sweep.is_none() {
+            zoom(&mut frame, cfg.zoom_rate);
+        }
+    }
+    Ok(())
+}
+// The text output main() writes when there is no out=: csv, orbit=, compare=, halfblock
+// or the plain render. its, when given, are the counts to draw instead of computing
+// them. notes adds the stderr lines that go with orbit= and --diff.
+fn write_text<W: Write>(
+    cfg: &Config,
+    other: Option<&Config>,
+    its: &mut Option<Vec<usize>>,
+    out: &mut W,
+    notes: bool,
+) -> io::Result<()> {
+    if cfg.format == Format::Csv {
+        let buf = its.get_or_insert_with(|| compute_iterations(cfg));
+        return write_csv(out, cfg, buf);
+    }
+    if let Some((cx, cy)) = cfg.orbit {
+        let points = orbit_points(cfg, cx, cy);
+        let escaped = points
+            .last()
+            .is_some_and(|(x, y)| x * x + y * y > cfg.bailout * cfg.bailout);
+        match escaped {
+            _ if !notes => {}
+            true => eprintln!("orbit: escaped at step {}", points.len() - 1),
+            false => eprintln!("orbit: bounded for {} steps", points.len() - 1),
+        }
+        out.write_all(render_orbit(cfg, &points).as_bytes())?;
+    } else if let Some(other) = other {
+        if cfg.diff {
+            let (a, b) = (compute_iterations(cfg), compute_iterations(other));
+            if notes {
+                let n = a
+                    .iter()
+                    .zip(&b)
+                    .filter(|&(&x, &y)| counts_differ(cfg, other, x, y))
+                    .count();
+                eprintln!("compare: {} of {} cells differ", n, a.len());
+            }
+            out.write_all(diff_map(cfg, other, &a, &b).as_bytes())?;
+        } else {
+            out.write_all(side_by_side(&render(cfg.clone()), &render(other.clone())).as_bytes())?;
+        }
+    } else if cfg.format == Format::Halfblock {
+        out.write_all(render_halfblock(cfg).as_bytes())?;
+    } else if cfg.stats || its.is_some() {
+        let buf = its.get_or_insert_with(|| compute_iterations(cfg));
+        out.write_all(render_iterations(cfg, buf).as_bytes())?;
+    } else {
+        render_streaming(cfg, out)?;
+    }
+    writeln!(out)
+}
+// --repeat-render-count: the output this Config asks for, written to a sink each time.
+fn bench_repeat(cfg: &Config, other: Option<&Config>) {
+    let mut times = Vec::with_capacity(cfg.repeat);
+    for _ in 0..cfg.repeat {
+        let t0 = Instant::now();
+        let written = match &cfg.out {
+            Some(path) => write_image(path, cfg, || Ok(io::sink())),
+            None => write_text(cfg, other, &mut None, &mut io::sink(), false),
+        };
+        if let Err(e) = written {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        times.push(t0.elapsed().as_secs_f64() * 1e3);
+    }
+    let min = times.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max = times.iter().cloned().fold(0.0, f64::max);
+    let avg = times.iter().sum::<f64>() / times.len() as f64;
+    eprintln!(
+        "render x{}: avg {:.3}ms min {:.3}ms max {:.3}ms ({}x{}, iters={})",
+        cfg.repeat, avg, min, max, cfg.width, cfg.height, cfg.iters
+    );
+}
+fn main() {
+    let (mut cfg, other) = match parse_args() {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if cfg.show_config {
+        println!("{:#?}", cfg);
+        return;
+    }
+    if cfg.repeat > 0 {
+        bench_repeat(&cfg, other.as_ref());
+        return;
+    }
+    if let Some(path) = &cfg.tour {
+        if let Err(e) = run_tour(&cfg, path) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if cfg.cycle {
+        if let Err(e) = cycle_palette(&cfg) {
+            eprint!("\x1b[?25h");
+            eprintln!("error: writing output: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if cfg.frames > 0 {
+        if let Err(e) = write_frames(&cfg) {
+            eprintln!("error: writing frames: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let mut its = cfg.sweep.map(|sweep| run_sweep(&mut cfg, sweep)); // reused by the render below
+    let t0 = Instant::now();
+    if let Some(path) = &cfg.out {
+        if let Err(e) = write_image(path, &cfg, || File::create(path).map(io::BufWriter::new)) {
+            eprintln!("error: writing {}: {}", path, e);
+            std::process::exit(1);
+        }
+    } else {
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        if let Err(e) = write_text(&cfg, other.as_ref(), &mut its, &mut out, true) {
+            eprintln!("error: writing output: {}", e);
+            std::process::exit(1);
+        }
+    }
+    let elapsed = t0.elapsed().as_secs_f64();
+    if TRUNCATED.load(Ordering::Relaxed) {
+        let ms = cfg.max_time.unwrap_or_default().as_millis();
+        eprintln!(
+            "warning: render truncated at maxtime={}; rows of '{}' were not computed",
+            ms, SKIPPED_GLYPH
+        );
+    }
+    if cfg.status_stdout {
+        println!("# {}", status_line(&cfg));
+    } else if !cfg.quiet {
+        eprintln!("{}", status_line(&cfg));
+    }
+    if cfg.stats {
+        let its = its.unwrap_or_else(|| compute_iterations(&cfg));
+        eprintln!("{}", iteration_stats(&its, cfg.iters));
+    }
+    if cfg.time {
+        let (w, h) = cfg.grid();
+        eprintln!(
+            "render: {:.1}ms ({} x {}, iters={}) {:.2} Mpixel/s",
+            elapsed * 1e3,
+            w,
+            h,
+            cfg.iters,
+            (w * h) as f64 / elapsed / 1e6
+        );
+        if cfg.threads > 1 {
+            let ms = |chunked| {
+                let t0 = Instant::now();
+                std::hint::black_box(parallel_rows(&cfg, chunked, |y| compute_row(&cfg, y)));
+                t0.elapsed().as_secs_f64() * 1e3
+            };
+            eprintln!(
+                "threads={}: shared row counter {:.1}ms, contiguous chunks {:.1}ms",
+                cfg.threads,
+                ms(false),
+                ms(true)
+            );
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn threads_match_single_thread() {
+        for (w, h, threads) in [(61, 31, 8), (61, 31, 3), (17, 5, 8), (40, 1, 4)] {
+            let one = Config {
+                width: w,
+                height: h,
+                ..Config::default()
+            };
+            let many = Config {
+                threads,
+                ..one.clone()
+            };
+            assert_eq!(
+                render(one.clone()),
+                render(many.clone()),
+                "{}x{} threads={}",
+                w,
+                h,
+                threads
+            );
+            assert_eq!(compute_colors(&one), compute_colors(&many));
+        }
+    }
+    #[test]
+    fn single_row_is_one_line_of_palette_glyphs() {
+        let s = render(Config {
+            width: 37,
+            height: 1,
+            ..Config::default()
+        });
+        assert_eq!(s.lines().count(), 1);
+        let line = s.lines().next().unwrap();
+        assert_eq!(line.chars().count(), 37);
+        assert!(line.chars().all(|c| PALETTE.contains(&c)), "{:?}", line);
+    }
+    #[test]
+    fn periodic_matches_plain_escape() {
+        let interior = [
+            (0.0, 0.0),
+            (-1.0, 0.0),
+            (-0.12, 0.74),
+            (0.25, 0.0),
+            (-1.75, 0.0),
+        ];
+        let exterior = [
+            (1.0, 0.0),
+            (0.5, 0.5),
+            (-2.1, 0.0),
+            (0.26, 0.0),
+            (-0.75, 0.1),
+        ];
+        for &(cx, cy) in interior.iter().chain(&exterior) {
+            let periodic = mandel_escape_periodic(0.0, 0.0, cx, cy, 500);
+            assert_eq!(
+                periodic,
+                mandel_escape(0.0, 0.0, cx, cy, 500),
+                "{} {}",
+                cx,
+                cy
+            );
+        }
+        assert_eq!(mandel_escape_periodic(0.0, 0.0, -1.0, 0.0, 500), 500);
+        assert!(mandel_escape_periodic(0.0, 0.0, 0.5, 0.5, 500) < 500);
+    }
+    #[test]
+    fn x4_lanes_match_mandel_escape() {
+        let cfg = Config {
+            width: 64,
+            height: 24,
+            ..Config::default()
+        };
+        for y in 0..24 {
+            for x in (0..64).step_by(4) {
+                let c: Vec<(f64, f64)> = (0..4).map(|l| cell_to_complex(&cfg, x + l, y)).collect();
+                let (re, im) = (
+                    [c[0].0, c[1].0, c[2].0, c[3].0],
+                    [c[0].1, c[1].1, c[2].1, c[3].1],
+                );
+                let lanes = mandel_escape_x4(re, im, cfg.iters);
+                for (l, &(u, v)) in c.iter().enumerate() {
+                    assert_eq!(
+                        lanes[l],
+                        mandel_escape(0.0, 0.0, u, v, cfg.iters),
+                        "cell {},{}",
+                        x + l,
+                        y
+                    );
+                }
+            }
+        }
+    }
+    #[test]
+    fn streaming_matches_render() {
+        let base = Config {
+            width: 61,
+            height: 19,
+            ..Config::default()
+        };
+        let views = [
+            base.clone(),
+            Config {
+                ruler: true,
+                legend: true,
+                ..base.clone()
+            },
+            Config {
+                color: ColorMode::Histogram,
+                ..base.clone()
+            },
+            Config {
+                dither: true,
+                transpose: true,
+                ..base.clone()
+            },
+            Config {
+                preview: 3,
+                outer_bands: 4,
+                show_axes: true,
+                ..base.clone()
+            },
+            Config {
+                threads: 4,
+                ruler: true,
+                dither: true,
+                ..base.clone()
+            },
+        ];
+        for cfg in views.iter() {
+            let mut streamed = Vec::new();
+            render_streaming(cfg, &mut streamed).unwrap();
+            assert_eq!(String::from_utf8(streamed).unwrap(), render(cfg.clone()));
+        }
+    }
+    #[test]
+    fn preview_halves_the_grid_over_the_same_bounds() {
+        let full = Config {
+            width: 80,
+            height: 40,
+            ..Config::default()
+        };
+        let half = Config {
+            preview: 2,
+            ..full.clone()
+        };
+        let s = render(half.clone());
+        assert_eq!(s.lines().count(), 20);
+        assert!(s.lines().all(|l| l.chars().count() == 40));
+        assert_eq!(cell_to_complex(&half, 0, 0), cell_to_complex(&full, 0, 0));
+        assert_eq!(
+            cell_to_complex(&half, 39, 19),
+            cell_to_complex(&full, 79, 39)
+        );
+    }
+    #[test]
+    fn zoom_out_saturates_at_max_scale() {
+        let mut cfg = Config::default();
+        for _ in 0..20 {
+            zoom(&mut cfg, 2.0);
+        }
+        assert_eq!(cfg.scale, MAX_SCALE);
+        // the taller side of a bounds rectangle is the one that reaches the limit
+        let mut cfg = Config::builder()
+            .bounds(-1.0, 1.0, -2.0, 2.0)
+            .unwrap()
+            .build();
+        for _ in 0..20 {
+            zoom(&mut cfg, 2.0);
+        }
+        let b = cfg.bounds.unwrap();
+        assert!((b[3] - b[2] - MAX_SCALE).abs() < 1e-12, "{:?}", b);
+        assert!((b[1] - b[0] - MAX_SCALE / 2.0).abs() < 1e-12, "{:?}", b);
+        assert_eq!(cfg.scale, b[1] - b[0]);
+    }
+    #[test]
+    fn bounds_outside_the_scale_limits_are_refused() {
+        let bounds = |xmin, xmax, ymin, ymax| Config::builder().bounds(xmin, xmax, ymin, ymax);
+        assert!(bounds(-10.0, 10.0, -1.0, 1.0).is_err());
+        assert!(bounds(-1.0, 1.0, -1e300, 1e300).is_err());
+        assert!(bounds(1.0, -1.0, 0.0, 1.0).is_err());
+        assert!(bounds(0.0, 1e-16, 0.0, 1.0).is_err());
+        let cfg = bounds(-2.0, 1.0, -1.0, 1.0).unwrap().build();
+        assert_eq!(cfg.bounds, Some([-2.0, 1.0, -1.0, 1.0]));
+        assert_eq!(cfg.scale, 3.0);
+    }
+    #[test]
+    fn csv_has_a_row_of_width_fields_per_image_row() {
+        let cfg = Config {
+            width: 23,
+            height: 7,
+            ..Config::default()
+        };
+        let mut out = Vec::new();
+        write_csv(&mut out, &cfg, &compute_iterations(&cfg)).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let rows: Vec<&str> = text.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(rows.len(), 7);
+        for row in rows {
+            let fields: Vec<&str> = row.split(',').collect();
+            assert_eq!(fields.len(), 23);
+            assert!(fields.iter().all(|f| f.parse::<usize>().is_ok()), "{}", row);
+        }
+    }
+    #[test]
+    fn outer_bands_leave_higher_bands_alone() {
+        let plain = Config::default();
+        let banded = Config {
+            outer_bands: 4,
+            ..plain.clone()
+        };
+        for it in 0..=plain.iters {
+            if shade(it, &plain) != PALETTE[0] {
+                assert_eq!(shade(it, &banded), shade(it, &plain), "it={}", it);
+            }
+        }
+    }
+    #[test]
+    fn origin_marked_only_when_on_screen() {
+        let on = Config {
+            width: 41,
+            height: 21,
+            show_origin: true,
+            ..Config::default()
+        };
+        let (x, y) = complex_to_cell(&on, 0.0, 0.0).expect("origin in the default view");
+        assert_eq!(overlay_row(&on, y, 41)[x], Some('+'));
+        let off = Config {
+            center_x: -1.5,
+            scale: 0.5,
+            ..on
+        };
+        assert_eq!(complex_to_cell(&off, 0.0, 0.0), None);
+        assert!((0..21).all(|y| overlay_row(&off, y, 41).iter().all(|m| m.is_none())));
+    }
+    #[test]
+    fn parity_glyphs() {
+        let cfg = Config {
+            palette: Palette::Parity,
+            ..Config::default()
+        };
+        assert_eq!(shade(10, &cfg), '.');
+        assert_eq!(shade(11, &cfg), '#');
+        assert_eq!(shade(cfg.iters, &cfg), '@');
+    }
+    #[test]
+    fn scene_centers_sit_near_the_boundary() {
+        for name in [
+            "seahorse-valley",
+            "elephant-valley",
+            "triple-spiral",
+            "mini-mandelbrot",
+        ]
+        .iter()
+        {
+            let cfg = Config::named_scene(name).unwrap();
+            let it = mandel_escape(0.0, 0.0, cfg.center_x, cfg.center_y, cfg.iters);
+            assert!(
+                it * 5 >= cfg.iters && it <= cfg.iters,
+                "{}: {} of {}",
+                name,
+                it,
+                cfg.iters
+            );
+        }
+    }
+    #[test]
+    fn transposed_square_view_is_the_matrix_transpose() {
+        let n = 31;
+        let cfg = Config {
+            width: n,
+            height: n,
+            center_y: 0.1,
+            ..Config::default()
+        };
+        let (its, flipped) = (
+            compute_iterations(&cfg),
+            compute_iterations(&Config {
+                transpose: true,
+                ..cfg
+            }),
+        );
+        for y in 0..n {
+            for x in 0..n {
+                assert_eq!(flipped[y * n + x], its[x * n + y], "cell {},{}", x, y);
+            }
+        }
+    }
+    #[test]
+    fn ruler_on_a_single_row_or_column() {
+        for (w, h) in [(40, 1), (1, 10)] {
+            let s = render(Config {
+                width: w,
+                height: h,
+                ruler: true,
+                ..Config::default()
+            });
+            assert_eq!(s.lines().count(), h + 2);
+        }
+    }
+    #[test]
+    fn region_tiles_keep_the_axes() {
+        let full = Config {
+            width: 80,
+            height: 21,
+            show_axes: true,
+            show_origin: true,
+            ..Config::default()
+        };
+        let whole = render(full.clone());
+        for [x0, y0, x1, y1] in [[0, 0, 30, 21], [40, 0, 80, 21], [10, 12, 50, 21]] {
+            let tile = render(Config {
+                region: Some([x0, y0, x1, y1]),
+                ..full.clone()
+            });
+            let rows: Vec<String> = whole
+                .lines()
+                .skip(y0)
+                .take(y1 - y0)
+                .map(|l| l[x0..x1].to_string())
+                .collect();
+            assert_eq!(
+                tile.lines().collect::<Vec<_>>(),
+                rows,
+                "region {},{},{},{}",
+                x0,
+                y0,
+                x1,
+                y1
+            );
+        }
+    }
+    #[test]
+    fn diff_map_ignores_the_interior_across_iters() {
+        let cfg = Config {
+            width: 40,
+            height: 12,
+            ..Config::default()
+        };
+        let other = Config {
+            iters: 200,
+            ..cfg.clone()
+        };
+        let (a, b) = (compute_iterations(&cfg), compute_iterations(&other));
+        let map = diff_map(&cfg, &other, &a, &b);
+        for (i, c) in map.lines().flat_map(|l| l.chars()).enumerate() {
+            assert_eq!(
+                c == '#',
+                a[i] != b[i] && (a[i] < cfg.iters || b[i] < other.iters),
+                "cell {}",
+                i
+            );
+        }
+    }
+    #[test]
+    fn grid_to_ascii_matches_render() {
+        let base = Config::default();
+        let views = [
+            base.clone(),
+            Config {
+                width: 37,
+                height: 11,
+                iters: 200,
+                ..base.clone()
+            },
+            Config {
+                palette: Palette::Gray,
+                mode: Mode::Julia,
+                ..base.clone()
+            },
+        ];
+        for cfg in views.iter() {
+            let grid = render_grid(cfg.clone()).to_ascii(&cfg.palette);
+            assert_eq!(grid, render(cfg.clone()));
+        }
+    }
+    #[test]
+    fn smooth_count_follows_the_bailout() {
+        for bailout in [2.0, 65536.0] {
+            let cfg = Config {
+                bailout,
+                ..Config::default()
+            };
+            let grid = render_grid(cfg.clone());
+            for cell in grid.cells.iter().filter(|c| c.iter < cfg.iters) {
+                assert!(
+                    (cell.smooth - cell.iter as f64).abs() <= 1.0,
+                    "{:?} at {}",
+                    cell,
+                    bailout
+                );
+            }
+        }
+    }
+    #[test]
+    fn interior_skip_matches_brute_force() {
+        let base = Config::default();
+        let views = [
+            base.clone(),
+            Config {
+                center_y: 1e200,
+                width: 10,
+                height: 2,
+                ..base.clone()
+            },
+            Config {
+                center_x: -0.7453,
+                center_y: 0.1127,
+                scale: 0.03,
+                iters: 300,
+                ..base.clone()
+            },
+            Config {
+                aa: 2,
+                power: 3,
+                ..base.clone()
+            },
+        ];
+        for cfg in views.iter() {
+            let brute = Config {
+                interior_skip: false,
+                ..cfg.clone()
+            };
+            assert_eq!(
+                compute_iterations(cfg),
+                compute_iterations(&brute),
+                "{:?}",
+                cfg
+            );
+        }
+    }
+    #[test]
+    fn julia_sweep_has_one_view_per_frame_from_start_to_target() {
+        let cfg = Config {
+            mode: Mode::Julia,
+            frames: 7,
+            julia_to: Some([-0.7, 0.27]),
+            ..Config::default()
+        };
+        let views = julia_sweep_configs(&cfg);
+        assert_eq!(views.len(), 7);
+        assert_eq!(
+            (views[0].julia_x, views[0].julia_y),
+            (cfg.julia_x, cfg.julia_y)
+        );
+        let last = &views[6];
+        assert!((last.julia_x + 0.7).abs() < 1e-12 && (last.julia_y - 0.27).abs() < 1e-12);
+        assert!(views
+            .iter()
+            .all(|v| v.center_x == cfg.center_x && v.scale == cfg.scale));
+        let one = Config {
+            frames: 1,
+            ..cfg.clone()
+        };
+        assert_eq!(julia_sweep_configs(&one).len(), 1);
+    }
 }